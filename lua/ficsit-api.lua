@@ -487,6 +487,35 @@ function FINComputerGPU:flush() end
 function FINComputerGPU:fill(x, y, w, h, str) self.buffer:fill(x, y, w, h, str) end
 function FINComputerGPU:setText(x, y, str) self.buffer:setText(x, y, str) end
 
+--- GPU T2 Mock
+--- Vektoren und Farben werden als FIN Structs übergeben, z.B. {x=0, y=0} und {r=1, g=1, b=1, a=1}.
+FINComputerGPUT2 = defineClass({
+	aliase = {"GPU_T2_C", "GPUT2"},
+	displayName = "Computer GPU T2"
+}, function(p)
+	p.screen=nil
+end)
+
+function FINComputerGPUT2:bindScreen(screen)
+	self.screen = screen
+end
+function FINComputerGPUT2:getScreen() return self.screen end
+function FINComputerGPUT2:getScreenSize() return {x=0, y=0} end
+function FINComputerGPUT2:flush() end
+function FINComputerGPUT2:drawRect(position, size, color, image, rotation) end
+function FINComputerGPUT2:drawLines(points, thickness, color) end
+function FINComputerGPUT2:drawBox(boxSettings) end
+function FINComputerGPUT2:drawText(position, text, size, color, monospace) end
+function FINComputerGPUT2:drawSpline(start, startDirection, stop, stopDirection, thickness, color) end
+function FINComputerGPUT2:drawBezier(p0, p1, p2, p3, thickness, color) end
+function FINComputerGPUT2:pushTransform(translation, rotation, scale) end
+function FINComputerGPUT2:pushLayout(offset, size, scale) end
+function FINComputerGPUT2:pushClipRect(position, size) end
+function FINComputerGPUT2:pushClipPolygon(topLeft, topRight, bottomRight, bottomLeft) end
+function FINComputerGPUT2:popGeometry() end
+function FINComputerGPUT2:popClip() end
+function FINComputerGPUT2:measureText(text, size, monospace) return {x=0, y=0} end
+
 FINComputerScreen = defineClass({
 	base = _Component,
 	aliase = {"Build_Screen_C", "Screen"},
@@ -4,24 +4,57 @@ ffi.cdef[[
 typedef struct { const char id[16]; uintptr_t h; } uid_handle;
 typedef struct { float r, g, b, a; } color;
 typedef struct { uint32_t width, height; } size;
-typedef struct { const char ch[4]; size_t l; color fg, bg; } cell;
-typedef struct { const char *val; size_t len; bool num; } param;
-typedef struct { const char *val; size_t len; } array;
-typedef struct { const char *e; const char cmp[16]; size_t len; param p[8]; } signal;
+typedef struct { const char ch[32]; size_t l; color fg, bg; } cell;
+typedef struct { uint8_t t; int64_t i; double f; const char *val; size_t len; } param;
+typedef struct { const char **val; size_t len; } array;
+typedef struct { uint32_t width, height; uint8_t *data; size_t len; } image;
+typedef struct { float x, y; } vector2;
+typedef struct { float x, y, z, w; } vector4;
+typedef struct { vector2 pos, size; float rot; color c; bool centered, rounded; vector4 radii; bool outline; float thickness; color oc; } box_settings;
+typedef struct { int32_t x, y; uint32_t width, height; double scale; } monitor;
+typedef struct { monitor *ptr; size_t len; } monitors;
+typedef struct { const char *e; const char cmp[16]; size_t len; param *p; } signal;
 uintptr_t new_event_handler();
 uintptr_t graphic_handle(uint32_t w, uint32_t h);
 void bind_screen(uintptr_t g, uintptr_t s);
 signal pull(uintptr_t g, float t);
+void free_signal(signal s);
+typedef struct event_filter event_filter;
+typedef struct event_queue event_queue;
+signal pull_filtered(uintptr_t h, event_filter *f, float t);
+event_filter *filter_new(param p);
+event_filter *filter_and(event_filter *a, event_filter *b);
+event_filter *filter_or(event_filter *a, event_filter *b);
+event_filter *filter_not(event_filter *a);
+bool filter_matches(event_filter *f, const signal *s);
+void filter_free(event_filter *f);
+event_queue *queue_new(uintptr_t h, event_filter *f);
+signal queue_pull(uintptr_t h, event_queue *q, event_filter *f, float t);
+void queue_free(event_queue *q);
+bool event_listen(uintptr_t h, const char *id);
+bool event_ignore(uintptr_t h, const char *id);
+void event_ignore_all(uintptr_t h);
+array event_listening(uintptr_t h);
 uid_handle create_screen(uint32_t f, uintptr_t h);
 void destroy_screen(uintptr_t s);
 void set_size(uintptr_t g, uint32_t w, uint32_t h);
 void set_location(uintptr_t s, int32_t x, int32_t y);
+void set_fonts(const char **p, size_t len);
+void set_monitor(uintptr_t s, int32_t i);
+void set_fullscreen(uintptr_t s, uint8_t m);
+void set_backend(uintptr_t s, uint8_t m);
+monitors get_monitors(uintptr_t s);
+void free_monitors(monitors m);
 void foreground(uintptr_t g, color c);
 void background(uintptr_t g, color c);
 void fill(uintptr_t g, int32_t x, int32_t y, int32_t w, int32_t h, const char *ch);
 void write_text(uintptr_t g, int32_t x, int32_t y, const char *ch);
-void write(uintptr_t g, int32_t x, int32_t y, const char *ch);
+void write_char(uintptr_t g, int32_t x, int32_t y, const char *ch);
 void flush(uintptr_t g);
+bool screenshot(uintptr_t g, const char *p, uint32_t f);
+uint32_t dirty_count(uintptr_t g);
+bool start_recording(uintptr_t g, const char *p, uint32_t f);
+int32_t stop_recording(uintptr_t g);
 uintptr_t get_buffer(uintptr_t g);
 void set_buffer(uintptr_t g, uintptr_t b);
 size buf_size(uintptr_t b);
@@ -31,7 +64,34 @@ uintptr_t buf_clone(uintptr_t b);
 void buf_fill(uintptr_t b, int32_t x, int32_t y, int32_t w, int32_t h, const char *ch, color fg, color bg);
 void buf_write(uintptr_t b, int32_t x, int32_t y, const char *ch, color fg, color bg);
 void buf_set(uintptr_t b, int32_t x, int32_t y, const char *ch, color fg, color bg);
+bool buf_set_raw(uintptr_t b, int32_t x, int32_t y, const char *ch, color *fg, size_t fl, color *bg, size_t bl);
 cell buf_get(uintptr_t b, uint32_t x, uint32_t y);
+image buf_render(uintptr_t b, uint32_t f);
+bool buf_save(uintptr_t b, const char *p);
+uintptr_t buf_load(const char *p);
+bool buf_save_snapshot(uintptr_t b, const char *p);
+array buf_compare_snapshot(uintptr_t b, const char *p);
+bool buf_save_image(uintptr_t b, const char *p, uint32_t f);
+array buf_compare_image(uintptr_t b, const char *p, uint32_t f, uint8_t t);
+void free_image(image i);
+void free_array(array a);
+uintptr_t gpu2_handle(uint32_t w, uint32_t h);
+void gpu2_bind_screen(uintptr_t g, uintptr_t s);
+vector2 gpu2_screen_size(uintptr_t g);
+void gpu2_draw_rect(uintptr_t g, vector2 p, vector2 s, color c, float r);
+void gpu2_draw_line(uintptr_t g, vector2 *p, size_t len, float t, color c);
+void gpu2_draw_box(uintptr_t g, box_settings b);
+void gpu2_draw_text(uintptr_t g, vector2 p, const char *ch, float s, color c, bool m);
+void gpu2_draw_spline(uintptr_t g, vector2 s, vector2 sd, vector2 e, vector2 ed, float t, color c);
+void gpu2_draw_bezier(uintptr_t g, vector2 p0, vector2 p1, vector2 p2, vector2 p3, float t, color c);
+void gpu2_push_transform(uintptr_t g, vector2 t, float r, vector2 s);
+void gpu2_push_layout(uintptr_t g, vector2 o, vector2 s, float f);
+void gpu2_push_clip_rect(uintptr_t g, vector2 p, vector2 s);
+void gpu2_push_clip_polygon(uintptr_t g, vector2 tl, vector2 tr, vector2 bl, vector2 br);
+void gpu2_pop_geometry(uintptr_t g);
+void gpu2_pop_clip(uintptr_t g);
+void gpu2_flush(uintptr_t g);
+vector2 gpu2_measure_text(uintptr_t g, const char *ch, float s, bool m);
 uid_handle create_network(uint16_t i, size_t b, uintptr_t h);
 bool open_port(uintptr_t n, uint16_t p);
 void close_port(uintptr_t n, uint16_t p);
@@ -39,8 +99,8 @@ void close_all_ports(uintptr_t n);
 void send_message(uintptr_t n, const char *ch, uint16_t p, param[?], size_t len);
 void broadcast_message(uintptr_t n, uint16_t p, param[?], size_t len);
 uintptr_t create_filesystem(const char *p, const char *n, uintptr_t h);
-bool mount(uintptr_t fs, const char *d, const char *m);
-bool unmount(uintptr_t fs, const char *d);
+bool fs_mount(uintptr_t fs, const char *d, const char *m);
+bool fs_unmount(uintptr_t fs, const char *d);
 bool fs_exists(uintptr_t fs, const char *f);
 bool fs_is_file(uintptr_t fs, const char *f);
 bool fs_is_dir(uintptr_t fs, const char *f);
@@ -49,17 +109,32 @@ bool fs_rename(uintptr_t fs, const char *f, const char *t);
 bool fs_create_dir(uintptr_t fs, const char *f);
 array fs_childs(uintptr_t fs, const char *f);
 ]]
-local libDir = debug.getinfo(1).source:match("@?(.*\\)")
---freen = ffi.load(libDir.."freen.dll")
-local freen = ffi.load("G:\\Satisfactory\\Freen\\target\\i686-pc-windows-msvc\\debug\\freen.dll")
+local libDir = debug.getinfo(1).source:match("@?(.*[\\/])") or ""
+local freen
+if ffi.os == "Windows" then
+	--freen = ffi.load(libDir.."freen.dll")
+	freen = ffi.load("G:\\Satisfactory\\Freen\\target\\i686-pc-windows-msvc\\debug\\freen.dll")
+elseif ffi.os == "OSX" then
+	freen = ffi.load(libDir.."libfreen.dylib")
+else
+	freen = ffi.load(libDir.."libfreen.so")
+end
 
 --[[
 Freen Konfigurations Objekt
 --]]
 FREEN = {
 	fontsize = 24,
+	--- Pfad zu einer Schriftart für das Zeichenraster. nil verwendet Fira Mono.
+	font = nil,
+	--- Ausweichschriftarten für Zeichen, die in font fehlen, z.B. Box Drawing.
+	fallbackFonts = {},
+	--- Ausgabe der Bildschirme: "window" oder "terminal".
+	--- "terminal" gibt eine GPU T1 mit ANSI Farben im aktuellen Terminal aus.
+	backend = "window",
+	gpu2Width = 1200,
+	gpu2Height = 800,
 	portStart = 10000,
-	maxNetworkArgs = 7,
 	networkBuffer = bit.lshift(1, 16), --64kb
 }
 
@@ -70,20 +145,221 @@ local SCREEN_CACHE = {}
 --- Der Eventhandler verarbeitet Signale von nativen Freen Componenten.
 local eventHandler = freen.new_event_handler()
 
+--- Übergibt die Schriftarten aus FREEN an Freen.
+local function apply_fonts()
+	local paths = {}
+	if FREEN.font ~= nil then table.insert(paths, FREEN.font) end
+	for _,f in ipairs(FREEN.fallbackFonts or {}) do
+		table.insert(paths, f)
+	end
+	freen.set_fonts(ffi.new("const char*[?]", #paths, paths), #paths)
+end
+
 --- Konvertiert eine interne Komponenten ID
 function __parseUID(c_id)
 	return ffi.string(c_id, 16)
 end
 
+--- Typen der Signal Parameter, wie in events.rs.
+local PARAM_NIL, PARAM_BOOL, PARAM_INT, PARAM_FLOAT = 0, 1, 2, 3
+local PARAM_STRING, PARAM_ARRAY, PARAM_STRUCT, PARAM_COMPONENT = 4, 5, 6, 7
+
+--- Wandelt einen Signal Parameter in den entsprechenden Lua Wert um.
+local function param_value(p)
+	local t = p.t
+	if t == PARAM_BOOL then
+		return p.i ~= 0
+	elseif t == PARAM_INT then
+		return tonumber(p.i)
+	elseif t == PARAM_FLOAT then
+		return tonumber(p.f)
+	elseif t == PARAM_STRING then
+		return ffi.string(p.val, p.len)
+	elseif t == PARAM_COMPONENT then
+		return component.proxy(ffi.string(p.val, p.len))
+	elseif t == PARAM_ARRAY then
+		local items = ffi.cast("const param*", p.val)
+		local result = {}
+		for i = 0,(tonumber(p.len)-1) do
+			result[i+1] = param_value(items[i])
+		end
+		return result
+	elseif t == PARAM_STRUCT then
+		local items = ffi.cast("const param*", p.val)
+		local result = {}
+		for i = 0,(tonumber(p.len)-1),2 do
+			result[param_value(items[i])] = param_value(items[i+1])
+		end
+		return result
+	end
+	return nil
+end
+
+--- Gibt die Argumente eines Signals und ihre Anzahl zurück. Argumente können nil sein.
 local function extract_signal_params(sig)
 	local args = {}
-	for i = 0,(sig.len-1) do
-		local p = sig.p[i]
-		local v = ffi.string(p.val, p.len)
-		if p.num then v = tonumber(v) end
-		table.insert(args, v)
+	local n = tonumber(sig.len)
+	for i = 0,(n-1) do
+		args[i+1] = param_value(sig.p[i])
+	end
+	return args, n
+end
+
+--- Wandelt einen Lua Wert in einen Signal Parameter um.
+--- Strings und verschachtelte Arrays werden in keep gesammelt, damit sie bis zum FFI Aufruf gültig bleiben.
+--- Tabellen sind entweder Arrays oder Structs, Tabellen mit beidem werden abgelehnt.
+local function encode_param(v, keep)
+	local t = type(v)
+	if t == 'boolean' then
+		return param(PARAM_BOOL, v and 1 or 0)
+	elseif t == 'number' then
+		if v == math.floor(v) and math.abs(v) < 2^53 then
+			return param(PARAM_INT, v)
+		end
+		return param(PARAM_FLOAT, 0, v)
+	elseif t == 'string' then
+		table.insert(keep, v)
+		return param(PARAM_STRING, 0, 0, v, #v)
+	elseif t == 'table' then
+		if v.id ~= nil and component.proxy(v.id) == v then
+			table.insert(keep, v.id)
+			return param(PARAM_COMPONENT, 0, 0, v.id, #v.id)
+		end
+		local items, n = {}, 0
+		local kind = PARAM_ARRAY
+		if #v > 0 or next(v) == nil then
+			n = #v
+			for k in pairs(v) do
+				if type(k) ~= 'number' or k < 1 or k > n or k ~= math.floor(k) then
+					error("Tables with both array items and named fields are not supported")
+				end
+			end
+			for i = 1,n do items[i] = v[i] end
+		else
+			kind = PARAM_STRUCT
+			for k,f in pairs(v) do
+				items[n+1] = tostring(k)
+				items[n+2] = f
+				n = n + 2
+			end
+		end
+		local array = ffi.new("param[?]", n)
+		for i = 1,n do
+			array[i-1] = encode_param(items[i], keep)
+		end
+		table.insert(keep, array)
+		return param(kind, 0, 0, ffi.cast("const char*", array), n)
+	end
+	return param(PARAM_NIL)
+end
+
+--- Kopiert ein String Array in eine Tabelle und gibt das Array frei.
+local function array_to_table(arr)
+	local t = {}
+	for i = 0,(tonumber(arr.len)-1) do
+		table.insert(t, ffi.string(arr.val[i]))
+	end
+	freen.free_array(arr)
+	return t
+end
+
+--- Wandelt FIN Farbwerte in ein FFI Array um.
+--- Wie in FIN sind es je Zeichen vier Zahlen (r, g, b, a), Tabellen mit Farben werden auch akzeptiert.
+local function color_array(c)
+	c = c or {}
+	local n = #c
+	if type(c[1]) ~= 'table' then n = math.floor(n / 4) end
+	local arr = ffi.new("color[?]", n)
+	for i = 0, n - 1 do
+		local v = c[i+1]
+		if type(v) == 'table' then
+			arr[i] = ffi.new("color", v.r or v[1] or 1, v.g or v[2] or 1, v.b or v[3] or 1, v.a or v[4] or 1)
+		else
+			arr[i] = ffi.new("color", c[i*4+1], c[i*4+2], c[i*4+3], c[i*4+4])
+		end
+	end
+	return arr, n
+end
+
+local function file_exists(path)
+	local f = io.open(path, "r")
+	if f == nil then return false end
+	f:close()
+	return true
+end
+
+--- Signale einer GPU T2, deren erste zwei Argumente ein FIN Vector2D sind.
+local T2_POSITION_EVENTS = {OnMouseDown=true, OnMouseUp=true, OnMouseMove=true, OnMouseWheel=true}
+
+--- Fasst die Pixel Position von GPU T2 Signalen zu einem Vector2D zusammen.
+local function t2_signal_params(name, comp, args, n)
+	if comp == nil or not comp._t2 or not T2_POSITION_EVENTS[name] then
+		return args, n
+	end
+	local result = {{x=args[1], y=args[2]}}
+	for i = 3,n do
+		result[i-1] = args[i]
+	end
+	return result, n-1
+end
+
+--- Native Komponenten werden im Eventhandler an- und abgemeldet,
+--- Signale ignorierter Komponenten werden schon an der Quelle verworfen.
+local org_event_listen = event.listen
+event.listen = function(comp)
+	if comp == nil or comp.id == nil then error("Invalid component", 2) end
+	if not freen.event_listen(eventHandler, comp.id) then
+		org_event_listen(comp)
+	end
+end
+
+local org_event_ignore = event.ignore
+event.ignore = function(comp)
+	if comp == nil or comp.id == nil then error("Invalid component", 2) end
+	if not freen.event_ignore(eventHandler, comp.id) then
+		org_event_ignore(comp)
+	end
+end
+
+local org_event_ignore_all = event.ignoreAll
+event.ignoreAll = function()
+	org_event_ignore_all()
+	freen.event_ignore_all(eventHandler)
+end
+
+local org_event_listening = event.listening
+event.listening = function()
+	local l = org_event_listening()
+	for _,id in ipairs(array_to_table(freen.event_listening(eventHandler))) do
+		local comp = component.proxy(id)
+		if comp ~= nil then table.insert(l, comp) end
+	end
+	return l
+end
+
+--- Mit event.registerListener registrierte Filter und Callbacks.
+local EVENT_LISTENERS = {}
+
+--- Wandelt ein natives Signal in die Rückgabewerte von event.pull um und gibt es frei.
+--- Passende Listener werden vorher aufgerufen.
+local function unpack_signal(sig)
+	if (sig.e == nil) then return nil end
+	local args, n = extract_signal_params(sig)
+	local comp = nil
+	if sig.cmp ~= nil then
+		comp = component.proxy(__parseUID(sig.cmp))
+	end
+	local name = ffi.string(sig.e)
+	local listeners = {}
+	for _,l in ipairs(EVENT_LISTENERS) do
+		if freen.filter_matches(l.filter._handle, sig) then table.insert(listeners, l.func) end
 	end
-	return args
+	freen.free_signal(sig)
+	args, n = t2_signal_params(name, comp, args, n)
+	for _,func in ipairs(listeners) do
+		func(name, comp, table.unpack(args, 1, n))
+	end
+	return name, comp, table.unpack(args, 1, n)
 end
 
 local org_event_pull = event.pull
@@ -92,20 +368,109 @@ event.pull = function(n)
 	if (sig[1] ~= nil) then
 		return unpack(sig)
 	end
-	n = n or 0.0
-	sig = freen.pull(eventHandler, n)
-	if (sig.e == nil) then
-		return nil
-	else
-		local args = extract_signal_params(sig)
-		local comp = nil
-		if sig.cmp ~= nil then
-			comp = component.proxy(__parseUID(sig.cmp))
-		end
-		return ffi.string(sig.e), comp, table.unpack(args)
+	return unpack_signal(freen.pull(eventHandler, n or 0.0))
+end
+
+local EventFilter = {}
+EventFilter.__index = EventFilter
+
+local function wrap_filter(ptr)
+	if ptr == nil then error("Invalid event filter", 3) end
+	return setmetatable({_handle = ffi.gc(ptr, freen.filter_free)}, EventFilter)
+end
+
+--- Verknüpft zwei Filter, beide müssen passen.
+--- LuaJIT kennt die Bit Operatoren von FIN nicht, daher steht * für und.
+function EventFilter.__mul(a, b)
+	return wrap_filter(freen.filter_and(a._handle, b._handle))
+end
+
+--- Verknüpft zwei Filter, einer muss passen.
+function EventFilter.__add(a, b)
+	return wrap_filter(freen.filter_or(a._handle, b._handle))
+end
+
+--- Negiert einen Filter.
+function EventFilter.__unm(a)
+	return wrap_filter(freen.filter_not(a._handle))
+end
+
+--- Erstellt einen Filter aus {event=..., sender=..., values={...}}.
+--- event und sender können einzelne Werte oder Arrays sein.
+--- values enthält Parameternamen wie in FIN oder 1-basierte Indizes mit den erwarteten Werten.
+--- Indizes zählen die nativen Argumente: Bei Mausereignissen einer GPU T2 sind x und y getrennt,
+--- obwohl event.pull sie als Vector2D liefert. Die Namen x, y und btn gelten für beide GPUs.
+--- Filter werden nur auf Signale nativer Freen Komponenten angewendet.
+event.filter = function(spec)
+	local keep = {}
+	return wrap_filter(freen.filter_new(encode_param(spec or {}, keep)))
+end
+
+--- Ruft func mit den Argumenten von event.pull auf, wenn ein gelesenes Signal zum Filter passt.
+event.registerListener = function(filter, func)
+	if type(func) ~= 'function' then error("Listener must be a function", 2) end
+	table.insert(EVENT_LISTENERS, {filter = filter, func = func})
+end
+
+--- Wartet auf das erste passende Signal. Übersprungene Signale bleiben für event.pull erhalten.
+event.waitFor = function(filter, timeout)
+	return unpack_signal(freen.pull_filtered(eventHandler, filter._handle, timeout or 0.0))
+end
+
+local EventQueue = {}
+EventQueue.__index = EventQueue
+
+--- Erstellt eine Queue, die ab jetzt Kopien aller passenden Signale sammelt.
+--- Ohne Filter werden alle Signale gesammelt.
+event.queue = function(filter)
+	local q = freen.queue_new(eventHandler, filter and filter._handle or nil)
+	return setmetatable({_handle = ffi.gc(q, freen.queue_free)}, EventQueue)
+end
+
+function EventQueue:pull(timeout)
+	return unpack_signal(freen.queue_pull(eventHandler, self._handle, nil, timeout or 0.0))
+end
+
+function EventQueue:waitFor(filter, timeout)
+	return unpack_signal(freen.queue_pull(eventHandler, self._handle, filter._handle, timeout or 0.0))
+end
+
+--- Prüft einen Buffer gegen einen Text Snapshot.
+--- Existiert der Snapshot noch nicht, wird er angelegt.
+function FREEN.assertSnapshot(buffer, path)
+	if not file_exists(path) then
+		buffer:saveSnapshot(path)
+		return
+	end
+	local diff = buffer:compareSnapshot(path)
+	if #diff > 0 then
+		error("Snapshot mismatch "..path.."\n"..table.concat(diff, "\n"), 2)
 	end
 end
 
+--- Prüft einen Buffer gegen ein Referenzbild.
+--- Existiert das Bild noch nicht, wird es angelegt.
+function FREEN.assertImage(buffer, path, tolerance)
+	if not file_exists(path) then
+		buffer:saveImage(path)
+		return
+	end
+	local diff = buffer:compareImage(path, tolerance)
+	if #diff > 0 then
+		error("Image mismatch "..path.."\n"..table.concat(diff, "\n"), 2)
+	end
+end
+
+--- Lädt einen mit GPUT1Buffer:save gespeicherten Buffer.
+--- Gibt nil zurück, wenn die Datei nicht gelesen werden kann.
+function FREEN.loadBuffer(path)
+	local handle = freen.buf_load(path)
+	if handle == 0 then return nil end
+	local buffer = GPUT1Buffer:new({})
+	buffer._handle = handle
+	return buffer
+end
+
 --- Schließt alle Fenster
 function FREEN:close()
 	for _,s in pairs(SCREEN_CACHE) do
@@ -152,6 +517,7 @@ end
 
 function FINComputerGPU:bindScreen(screen)
 	freen.bind_screen(self._handle, screen._handle)
+	screen._t2 = nil
 	self.screen = screen
 end
 
@@ -160,6 +526,34 @@ function FINComputerGPU:setLocation(x, y)
 	freen.set_location(self.screen._handle, x, y)
 end
 
+-- Freen Exklusive Funktion
+function FINComputerGPU:screenshot(path)
+	return freen.screenshot(self._handle, path, FREEN.fontsize)
+end
+
+-- Freen Exklusive Funktion
+--- Startet eine Aufnahme, die bei jedem flush ein Bild aufzeichnet.
+--- Das Format folgt der Dateiendung: .gif oder .png für APNG.
+function FINComputerGPU:startRecording(path)
+	return freen.start_recording(self._handle, path, FREEN.fontsize)
+end
+
+-- Freen Exklusive Funktion
+--- Beendet die Aufnahme und speichert die Animation mit den echten Bildzeiten.
+--- Gibt die Anzahl der geschriebenen Bilder zurück, nil bei einem Fehler.
+--- GIF verwirft Bilder, die kürzer als 10ms angezeigt wurden.
+function FINComputerGPU:stopRecording()
+	local frames = freen.stop_recording(self._handle)
+	if frames < 0 then return nil end
+	return frames
+end
+
+-- Freen Exklusive Funktion
+--- Anzahl der geänderten Bereiche, die beim nächsten Bild hochgeladen werden.
+function FINComputerGPU:getDirtyCount()
+	return freen.dirty_count(self._handle)
+end
+
 function FINComputerGPU:getBuffer()
 	local buffer = GPUT1Buffer:new({})
 	buffer._handle = freen.get_buffer(self._handle)
@@ -208,7 +602,39 @@ function GPUT1Buffer:get(x, y, buffer)
 end
 
 function GPUT1Buffer:setRaw(x, y, c, fg, bg)
-	freen.buf_copy(self._handle, x, y, c, fg, bg)
+	local fga, fgl = color_array(fg)
+	local bga, bgl = color_array(bg)
+	return freen.buf_set_raw(self._handle, x, y, c, fga, fgl, bga, bgl)
+end
+
+-- Freen Exklusive Funktion
+--- Speichert den Buffer verlustfrei als Binärdatei.
+function GPUT1Buffer:save(path)
+	return freen.buf_save(self._handle, path)
+end
+
+-- Freen Exklusive Funktion
+--- Speichert Zeichen und Farben des Buffers als Text Snapshot.
+function GPUT1Buffer:saveSnapshot(path)
+	return freen.buf_save_snapshot(self._handle, path)
+end
+
+-- Freen Exklusive Funktion
+--- Vergleicht den Buffer mit einem Text Snapshot und gibt die abweichenden Zellen zurück.
+function GPUT1Buffer:compareSnapshot(path)
+	return array_to_table(freen.buf_compare_snapshot(self._handle, path))
+end
+
+-- Freen Exklusive Funktion
+--- Speichert ein mit dem CPU Rasterizer gerendertes Referenzbild.
+function GPUT1Buffer:saveImage(path)
+	return freen.buf_save_image(self._handle, path, FREEN.fontsize)
+end
+
+-- Freen Exklusive Funktion
+--- Vergleicht den gerenderten Buffer mit einem Referenzbild und gibt die abweichenden Zellen zurück.
+function GPUT1Buffer:compareImage(path, tolerance)
+	return array_to_table(freen.buf_compare_image(self._handle, path, FREEN.fontsize, tolerance or 0))
 end
 
 function GPUT1Buffer:clone()
@@ -223,19 +649,169 @@ function FINComputerGPU:init()
 	self.screen=nil
 	self.fg = {r=1.0,g=1.0,b=1.0,a=1.0}
 	self.bg = {r=0.0,g=0.0,b=0.0,a=1.0}
+	apply_fonts()
 	self._handle = freen.graphic_handle(self._width, self._height)
 end
 
+--- Wandelt einen FIN Vector2D in einen FFI Vektor um.
+local function vec2(v)
+	v = v or {}
+	return ffi.new("vector2", v.x or v[1] or 0, v.y or v[2] or 0)
+end
+
+local function vec2_table(v)
+	return {x=v.x, y=v.y}
+end
+
+--- Wandelt eine FIN Farbe in eine FFI Farbe um. Fehlende Werte sind 1.
+local function col(c)
+	c = c or {}
+	return ffi.new("color", c.r or c[1] or 1, c.g or c[2] or 1, c.b or c[3] or 1, c.a or c[4] or 1)
+end
+
+function FINComputerGPUT2:init()
+	self.screen=nil
+	self._handle = freen.gpu2_handle(FREEN.gpu2Width, FREEN.gpu2Height)
+end
+
+function FINComputerGPUT2:bindScreen(screen)
+	if screen == nil then
+		freen.gpu2_bind_screen(self._handle, nil)
+	else
+		freen.gpu2_bind_screen(self._handle, screen._handle)
+		-- Maus Signale werden nun in Pixeln gesendet.
+		screen._t2 = true
+	end
+	self.screen = screen
+end
+
+function FINComputerGPUT2:getScreenSize()
+	return vec2_table(freen.gpu2_screen_size(self._handle))
+end
+
+function FINComputerGPUT2:flush()
+	freen.gpu2_flush(self._handle)
+end
+
+--- Bilder werden von Freen nicht unterstützt und ignoriert.
+function FINComputerGPUT2:drawRect(position, size, color, image, rotation)
+	freen.gpu2_draw_rect(self._handle, vec2(position), vec2(size), col(color), rotation or 0)
+end
+
+function FINComputerGPUT2:drawLines(points, thickness, color)
+	local array = ffi.new("vector2[?]", #points)
+	for i,p in ipairs(points) do
+		array[i-1] = vec2(p)
+	end
+	freen.gpu2_draw_line(self._handle, array, #points, thickness or 1, col(color))
+end
+
+function FINComputerGPUT2:drawBox(box)
+	local settings = ffi.new("box_settings")
+	settings.pos = vec2(box.position)
+	settings.size = vec2(box.size)
+	settings.rot = box.rotation or 0
+	settings.c = col(box.color)
+	settings.centered = box.hasCenteredOrigin or false
+	settings.rounded = box.isRounded or false
+	local r = box.radii or {}
+	settings.radii = ffi.new("vector4", r.x or r[1] or 0, r.y or r[2] or 0, r.z or r[3] or 0, r.w or r[4] or 0)
+	settings.outline = box.hasOutline or false
+	settings.thickness = box.outlineThickness or 0
+	settings.oc = col(box.outlineColor)
+	freen.gpu2_draw_box(self._handle, settings)
+end
+
+function FINComputerGPUT2:drawText(position, text, size, color, monospace)
+	freen.gpu2_draw_text(self._handle, vec2(position), tostring(text), size, col(color), monospace or false)
+end
+
+function FINComputerGPUT2:drawSpline(start, startDirection, stop, stopDirection, thickness, color)
+	freen.gpu2_draw_spline(self._handle, vec2(start), vec2(startDirection), vec2(stop), vec2(stopDirection), thickness or 1, col(color))
+end
+
+function FINComputerGPUT2:drawBezier(p0, p1, p2, p3, thickness, color)
+	freen.gpu2_draw_bezier(self._handle, vec2(p0), vec2(p1), vec2(p2), vec2(p3), thickness or 1, col(color))
+end
+
+function FINComputerGPUT2:pushTransform(translation, rotation, scale)
+	freen.gpu2_push_transform(self._handle, vec2(translation), rotation or 0, vec2(scale or {x=1, y=1}))
+end
+
+function FINComputerGPUT2:pushLayout(offset, size, scale)
+	freen.gpu2_push_layout(self._handle, vec2(offset), vec2(size), scale or 1)
+end
+
+function FINComputerGPUT2:pushClipRect(position, size)
+	freen.gpu2_push_clip_rect(self._handle, vec2(position), vec2(size))
+end
+
+function FINComputerGPUT2:pushClipPolygon(topLeft, topRight, bottomRight, bottomLeft)
+	freen.gpu2_push_clip_polygon(self._handle, vec2(topLeft), vec2(topRight), vec2(bottomLeft), vec2(bottomRight))
+end
+
+function FINComputerGPUT2:popGeometry()
+	freen.gpu2_pop_geometry(self._handle)
+end
+
+function FINComputerGPUT2:popClip()
+	freen.gpu2_pop_clip(self._handle)
+end
+
+function FINComputerGPUT2:measureText(text, size, monospace)
+	return vec2_table(freen.gpu2_measure_text(self._handle, tostring(text), size, monospace or false))
+end
+
+--- Ausgaben für FREEN.backend.
+local SCREEN_BACKENDS = {window = 0, terminal = 1}
+
 local Freen = defineClass({
 	aliase = {"Freen", "Screen", "Build_Screen_C", "FINComputerScreen"},
 	displayName = "Freen Window"
 }, function(p)
+	apply_fonts()
+	local backend = SCREEN_BACKENDS[FREEN.backend or "window"]
+	if backend == nil then error("Unknown screen backend "..tostring(FREEN.backend), 3) end
 	local c = freen.create_screen(FREEN.fontsize, eventHandler)
 	p.id = __parseUID(c.id)
 	p._handle = c.h
+	freen.set_backend(p._handle, backend)
 	table.insert(SCREEN_CACHE, p)
 end)
 
+--- Vollbild Modi für Freen:setFullscreen.
+local FULLSCREEN_MODES = {windowed = 0, borderless = 1, exclusive = 2}
+
+-- Freen Exklusive Funktion
+--- Setzt den Vollbild Modus: "windowed", "borderless" oder "exclusive".
+--- Kann auch vor dem Öffnen des Fensters aufgerufen werden.
+function Freen:setFullscreen(mode)
+	local m = FULLSCREEN_MODES[mode or "borderless"]
+	if m == nil then error("Unknown fullscreen mode "..tostring(mode), 2) end
+	freen.set_fullscreen(self._handle, m)
+end
+
+-- Freen Exklusive Funktion
+--- Wählt den Monitor mit dem Index aus getMonitors. nil wählt den aktuellen Monitor.
+function Freen:setMonitor(index)
+	freen.set_monitor(self._handle, (index or 0) - 1)
+end
+
+-- Freen Exklusive Funktion
+--- Gibt Position und Größe aller Monitore zurück, auch bevor das Fenster geöffnet ist.
+--- Gibt nil zurück, wenn keine Monitore abgefragt werden können, etwa ohne Display oder unter macOS.
+function Freen:getMonitors()
+	local m = freen.get_monitors(self._handle)
+	if m.ptr == nil then return nil end
+	local result = {}
+	for i = 0,(tonumber(m.len)-1) do
+		local info = m.ptr[i]
+		table.insert(result, {x=info.x, y=info.y, width=info.width, height=info.height, scale=info.scale})
+	end
+	freen.free_monitors(m)
+	return result
+end
+
 function Freen:close()
 	if (self._handle ~= nil) then
 		freen.destroy_screen(self._handle)
@@ -281,32 +857,29 @@ function NetworkCard:closeAll()
 	freen.close_all_ports(self._handle)
 end
 
-local function network_data(args)
-	if #args > FREEN.maxNetworkArgs then error("Too many arguments", 3) end
-	local array = ffi.new("param[?]", #args)
-	for i,a in pairs(args) do
-		-- TODO: Alle Argumente werden momentan von freen als String gesendet.
-		--local n = type(a) == 'number'
-		a = tostring(a)
-		array[i-1] = param(a, #a, false)
+local function network_data(args, n)
+	local keep = {}
+	local array = ffi.new("param[?]", n)
+	for i = 1,n do
+		array[i-1] = encode_param(args[i], keep)
 	end
-	return array
+	return array, keep
 end
 
 function NetworkCard:send(rec, port, ...)
 	--print("send", ...)
 	if rec == nil then error("reciever is nil") end
 	
-	local args = {...}
-	local array = network_data(args)
-	freen.send_message(self._handle, rec, tonumber(port), array, #args)
+	local n = select('#', ...)
+	local array, keep = network_data({...}, n)
+	freen.send_message(self._handle, rec, tonumber(port), array, n)
 end
 
 function NetworkCard:broadcast(port, ...)
 	--print("broadcast", ...)
-	local args = {...}
-	local array = network_data(args)
-	freen.broadcast_message(self._handle, tonumber(port), array, #args)
+	local n = select('#', ...)
+	local array, keep = network_data({...}, n)
+	freen.broadcast_message(self._handle, tonumber(port), array, n)
 end
 
 local freen_fs = nil
@@ -321,12 +894,12 @@ end
 
 filesystem.mount = function(device, mountPoint)
 	check_fs()
-	return freen.mount(freen_fs, device, mountPoint)
+	return freen.fs_mount(freen_fs, device, mountPoint)
 end
 
 filesystem.unmount = function(device)
 	check_fs()
-	return freen.unmount(freen_fs, device)
+	return freen.fs_unmount(freen_fs, device)
 end
 
 filesystem.exists = function(file)
@@ -5,6 +5,7 @@ use rand::distributions::{Distribution, Uniform};
 use crate::events::EventEmitter;
 
 pub const UID_SIZE: usize = 16;
+#[allow(clippy::upper_case_acronyms)]
 pub type UID = [u8; UID_SIZE];
 
 //pub static EMPTY_UID: UID = 0;
@@ -30,6 +31,7 @@ pub trait Component
 	fn listen(&mut self, emitter: Option<EventEmitter>);
 }
 
+#[allow(dead_code)]
 pub struct GenericComponent
 {
 	id: UID,
@@ -58,9 +60,9 @@ pub fn generateUID() -> UID
 	let mut id = [0; UID_SIZE];
 	unsafe
 	{
-		for i in 0..UID_SIZE
+		for digit in id.iter_mut()
 		{
-			id[i] = *HEX_DIGITS.offset(hex.sample(&mut rng)) as u8;
+			*digit = *HEX_DIGITS.offset(hex.sample(&mut rng)) as u8;
 		}
 	}
 	id
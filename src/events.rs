@@ -2,26 +2,44 @@
 
 use core::panic;
 use std::error::Error;
-use std::ffi::CString;
+use std::collections::{HashSet, VecDeque};
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
 use std::ptr;
 use std::slice;
 use std::sync::mpsc;
-use std::time::Duration;
-use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::sync::{Arc, Mutex, Weak};
+
 
 use crate::component::UID;
 
 
+/// Komponenten, deren Signale zugestellt werden.
+type Listeners = Arc<Mutex<HashSet<UID>>>;
+
+/// Maximale Anzahl zurückgehaltener Signale im Handler und in jeder Queue.
+/// Darüber hinaus wird das älteste Signal verworfen, damit ungelesene Signale den Speicher nicht füllen.
+pub const MAX_PENDING_SIGNALS: usize = 1024;
+
+#[derive(Clone)]
 pub struct EventEmitter
 {
 	sender: Arc<Mutex<mpsc::Sender<Signal>>>,
+	listeners: Listeners,
 	owner: UID
 }
 
 impl EventEmitter
 {
+	/// Signale von Komponenten, die nicht angemeldet sind, werden verworfen.
+	/// Signale ohne Komponente, wie die des Dateisystems, werden immer zugestellt.
 	pub fn send(&mut self, event: Signal)
 	{
+		if self.owner != UID::default() && !self.listeners.lock().unwrap().contains(&self.owner)
+		{
+			return;
+		}
 		if let Err(e) = self.sender.lock().unwrap().send(event)
 		{
 			eprintln!("Event Error {}", e);
@@ -34,7 +52,13 @@ impl EventEmitter
 pub struct EventHandler
 {
 	sender: Arc<Mutex<mpsc::Sender<Signal>>>,
-	recever: Arc<Mutex<mpsc::Receiver<Signal>>>
+	recever: Arc<Mutex<mpsc::Receiver<Signal>>>,
+	listeners: Listeners,
+	/// Komponenten, für die schon ein Emitter erstellt wurde.
+	known: HashSet<UID>,
+	/// Von poll_filtered übersprungene Signale, höchstens [`MAX_PENDING_SIGNALS`]
+	pending: VecDeque<Signal>,
+	queues: Vec<Weak<Mutex<EventQueue>>>,
 }
 
 impl EventHandler
@@ -45,7 +69,11 @@ impl EventHandler
 		Self
 		{
 			sender: Arc::new( Mutex::new(sender)),
-			recever: Arc::new( Mutex::new(recever))
+			recever: Arc::new( Mutex::new(recever)),
+			listeners: Listeners::default(),
+			known: HashSet::new(),
+			pending: VecDeque::new(),
+			queues: Vec::new(),
 		}
 	}
 
@@ -54,35 +82,163 @@ impl EventHandler
 		&self.sender
 	}
 
-	pub fn new_emitter(&self, owner: UID) -> EventEmitter
+	/// Erstellt einen Emitter für eine Komponente.
+	/// Anders als in FIN ist die Komponente beim ersten Emitter sofort angemeldet,
+	/// damit Freen Signale wie WindowClosed ohne event.listen ankommen.
+	/// Weitere Emitter, etwa nach bindScreen, melden eine ignorierte Komponente nicht wieder an.
+	pub fn new_emitter(&mut self, owner: UID) -> EventEmitter
+	{
+		if self.known.insert(owner)
+		{
+			self.listen(owner);
+		}
+		EventEmitter{sender: self.sender.clone(), listeners: self.listeners.clone(), owner}
+	}
+
+	pub fn listen(&self, owner: UID)
+	{
+		if owner != UID::default()
+		{
+			self.listeners.lock().unwrap().insert(owner);
+		}
+	}
+
+	pub fn ignore(&self, owner: UID)
 	{
-		EventEmitter{sender: self.sender.clone(), owner}
+		self.listeners.lock().unwrap().remove(&owner);
+	}
+
+	pub fn ignore_all(&self)
+	{
+		self.listeners.lock().unwrap().clear();
+	}
+
+	pub fn listening(&self) -> Vec<UID>
+	{
+		let mut uids: Vec<UID> = self.listeners.lock().unwrap().iter().copied().collect();
+		uids.sort();
+		uids
 	}
 
 	pub fn poll(&mut self, timeout: Option<Duration>) -> Result<Signal, Box<dyn Error + Send>>
 	{
-		match timeout
+		if let Some(signal) = self.pending.pop_front()
 		{
-			Some(duration) => {
-				let result = self.recever.lock().unwrap().recv_timeout(duration);
+			return Ok(signal);
+		}
+		Ok(self.receive(deadline(timeout)).unwrap_or_else(Signal::default))
+	}
+
+	/// Liefert das erste zum Filter passende Signal.
+	/// Übersprungene Signale bleiben in ihrer Reihenfolge für poll erhalten,
+	/// bis mehr als [`MAX_PENDING_SIGNALS`] zurückgehalten werden.
+	pub fn poll_filtered(&mut self, filter: &EventFilter, timeout: Option<Duration>) -> Signal
+	{
+		if let Some(idx) = self.pending.iter().position(|s| filter.matches(s))
+		{
+			return self.pending.remove(idx).unwrap();
+		}
+		let deadline = deadline(timeout);
+		while let Some(signal) = self.receive(deadline)
+		{
+			if filter.matches(&signal) { return signal; }
+			push_bounded(&mut self.pending, signal);
+		}
+		Signal::default()
+	}
 
-				match result {
-					Ok(v) => Ok(v),
-					_ => Ok(Signal::default()),
+	/// Erstellt eine Queue, die ab jetzt Kopien aller passenden Signale sammelt.
+	/// Die Queue wird nicht mehr befüllt, sobald alle Referenzen außerhalb des Handlers freigegeben sind.
+	pub fn create_queue(&mut self, filter: EventFilter) -> Arc<Mutex<EventQueue>>
+	{
+		// Bereits gesendete Signale gehören nicht in die neue Queue.
+		while let Some(signal) = self.receive(None)
+		{
+			push_bounded(&mut self.pending, signal);
+		}
+		let queue = Arc::new(Mutex::new(EventQueue{ filter, signals: VecDeque::new() }));
+		self.queues.push(Arc::downgrade(&queue));
+		queue
+	}
+
+	/// Liefert das erste Signal der Queue, das zum optionalen Filter passt.
+	pub fn poll_queue(&mut self, queue: &Mutex<EventQueue>, filter: Option<&EventFilter>, timeout: Option<Duration>) -> Signal
+	{
+		let deadline = deadline(timeout);
+		loop
+		{
+			{
+				let mut queue = queue.lock().unwrap();
+				if let Some(idx) = queue.signals.iter().position(|s| filter.is_none_or(|f| f.matches(s)))
+				{
+					return queue.signals.remove(idx).unwrap();
 				}
 			}
-			None => {
-				let result = { self.recever.lock().unwrap().try_recv() };
+			match self.receive(deadline)
+			{
+				Some(signal) => push_bounded(&mut self.pending, signal),
+				None => return Signal::default(),
+			}
+		}
+	}
 
-				match result {
-					Ok(v) => Ok(v),
-					_ => Ok(Signal::default()),
-				}	
+	/// Empfängt ein Signal und verteilt Kopien an alle Queues.
+	/// Ohne Frist wird nicht gewartet.
+	fn receive(&mut self, deadline: Option<Instant>) -> Option<Signal>
+	{
+		let signal = {
+			let recever = self.recever.lock().unwrap();
+			match deadline
+			{
+				Some(deadline) => recever.recv_timeout(deadline.saturating_duration_since(Instant::now())).ok(),
+				None => recever.try_recv().ok(),
 			}
+		}?;
+		self.queues.retain(|queue| match queue.upgrade()
+		{
+			Some(queue) => { queue.lock().unwrap().offer(&signal); true },
+			None => false,
+		});
+		Some(signal)
+	}
+}
+
+#[inline]
+fn deadline(timeout: Option<Duration>) -> Option<Instant>
+{
+	timeout.map(|t| Instant::now() + t)
+}
+
+/// Hängt ein Signal an und verwirft bei Überlauf das älteste.
+#[inline]
+fn push_bounded(signals: &mut VecDeque<Signal>, signal: Signal)
+{
+	if signals.len() >= MAX_PENDING_SIGNALS
+	{
+		signals.pop_front();
+	}
+	signals.push_back(signal);
+}
+
+/// Sammelt Signale für event.queue, höchstens [`MAX_PENDING_SIGNALS`].
+pub struct EventQueue
+{
+	filter: EventFilter,
+	signals: VecDeque<Signal>,
+}
+
+impl EventQueue
+{
+	fn offer(&mut self, signal: &Signal)
+	{
+		if self.filter.matches(signal)
+		{
+			push_bounded(&mut self.signals, signal.clone());
 		}
 	}
 }
 
+/// FFI Array aus C Strings. Der Speicher gehört dem Aufrufer und muss mit `free_array` freigegeben werden.
 #[repr(C)]
 pub struct C_Array
 {
@@ -92,94 +248,189 @@ pub struct C_Array
 
 impl C_Array
 {
+	/// Kopiert die Strings als nullterminierte C Strings. Nullzeichen im Text werden entfernt.
 	pub fn new<T: AsRef<str>>(array: &[T]) -> Self
 	{
-		let mut result = Vec::<*const u8>::with_capacity(array.len());
-		let len = array.len();
-		for s in array
-		{
-			let str = s.as_ref().to_owned();
-			result.push(str.as_ptr());
-			std::mem::forget(str);
-		}
-		let ptr = result.as_ptr();
-		std::mem::forget(result);
-		Self { ptr, len }
+		let strings: Box<[*const u8]> = array.iter()
+			.map(|s| CString::new(s.as_ref().replace('\0', "")).unwrap().into_raw() as *const u8)
+			.collect();
+		let len = strings.len();
+		Self { ptr: Box::into_raw(strings) as *const *const u8, len }
 	}
 
-	pub unsafe fn get(&self, i: isize) -> String
+	pub unsafe fn free(self)
 	{
-		let ptr = self.ptr.offset(i).read();
-		CString::from_raw(ptr as *mut i8).into_string().unwrap()
+		if self.ptr.is_null() { return; }
+		let strings = Box::from_raw(ptr::slice_from_raw_parts_mut(self.ptr as *mut *const u8, self.len));
+		for s in strings.iter()
+		{
+			drop(CString::from_raw(*s as *mut c_char));
+		}
 	}
 }
 
+/// Typen eines [`C_Param`], wie in freen.lua.
+pub const PARAM_NIL: u8 = 0;
+pub const PARAM_BOOL: u8 = 1;
+pub const PARAM_INT: u8 = 2;
+pub const PARAM_FLOAT: u8 = 3;
+pub const PARAM_STRING: u8 = 4;
+pub const PARAM_ARRAY: u8 = 5;
+pub const PARAM_STRUCT: u8 = 6;
+pub const PARAM_COMPONENT: u8 = 7;
+
+/// Wert eines Signal Parameters mit den Typen, die FIN Signale transportieren.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value
+{
+	Nil,
+	Bool(bool),
+	Int(i64),
+	Float(f64),
+	String(String),
+	Array(Vec<Value>),
+	/// Felder eines FIN Structs wie Color oder Vector.
+	Struct(Vec<(String, Value)>),
+	/// Referenz auf eine Komponente über ihre ID.
+	Component(String),
+}
+
+impl From<bool> for Value { fn from(v: bool) -> Self { Value::Bool(v) } }
+impl From<i32> for Value { fn from(v: i32) -> Self { Value::Int(v as i64) } }
+impl From<u32> for Value { fn from(v: u32) -> Self { Value::Int(v as i64) } }
+impl From<u16> for Value { fn from(v: u16) -> Self { Value::Int(v as i64) } }
+impl From<i64> for Value { fn from(v: i64) -> Self { Value::Int(v) } }
+impl From<f32> for Value { fn from(v: f32) -> Self { Value::Float(v as f64) } }
+impl From<f64> for Value { fn from(v: f64) -> Self { Value::Float(v) } }
+impl From<char> for Value { fn from(v: char) -> Self { Value::String(v.to_string()) } }
+impl From<&str> for Value { fn from(v: &str) -> Self { Value::String(v.to_string()) } }
+impl From<String> for Value { fn from(v: String) -> Self { Value::String(v) } }
+
+/// FFI Darstellung eines [`Value`].
+/// Bool und Int stehen in `int`, Float in `float`.
+/// Bei Strings und Komponenten zeigt `ptr` auf UTF-8 Bytes der Länge `len`.
+/// Bei Arrays zeigt `ptr` auf `len` weitere Parameter,
+/// bei Structs auf `len` Parameter mit abwechselnd Schlüssel und Wert.
 #[repr(C)]
 #[derive(Copy, Clone)]
 pub struct C_Param
 {
+	pub kind: u8,
+	pub int: i64,
+	pub float: f64,
 	pub ptr: *const u8,
 	pub len: usize,
-	pub is_num: bool
 }
 
 impl C_Param
 {
-	pub fn from_str(str: &str) -> Self
+	pub fn new(value: &Value) -> Self
 	{
-		let len = str.len();
-		let ptr = str.as_bytes().as_ptr();
-		std::mem::forget(str);
+		let mut param = Self::default();
+		match value
+		{
+			Value::Nil => {},
+			Value::Bool(v) => { param.kind = PARAM_BOOL; param.int = *v as i64; },
+			Value::Int(v) => { param.kind = PARAM_INT; param.int = *v; },
+			Value::Float(v) => { param.kind = PARAM_FLOAT; param.float = *v; },
+			Value::String(v) => { param.kind = PARAM_STRING; param.set_bytes(v); },
+			Value::Component(v) => { param.kind = PARAM_COMPONENT; param.set_bytes(v); },
+			Value::Array(items) => {
+				param.kind = PARAM_ARRAY;
+				param.set_params(items.iter().map(C_Param::new).collect());
+			},
+			Value::Struct(fields) => {
+				param.kind = PARAM_STRUCT;
+				param.set_params(fields.iter()
+					.flat_map(|(k, v)| [C_Param::new(&Value::String(k.clone())), C_Param::new(v)])
+					.collect());
+			},
+		}
+		param
+	}
 
-		Self { ptr, len, is_num: false }
+	#[inline]
+	pub fn default() -> Self
+	{
+		Self { kind: PARAM_NIL, int: 0, float: 0.0, ptr: ptr::null(), len: 0 }
 	}
 
-	pub fn from<T>(v: &T, is_num: bool) -> Self
-	where T: ToString
+	fn set_bytes(&mut self, str: &str)
 	{
-		let str = v.to_string();
-		let len = str.len();
-		let ptr = str.as_bytes().as_ptr();
-		std::mem::forget(str);
+		let bytes: Box<[u8]> = str.as_bytes().into();
+		self.len = bytes.len();
+		self.ptr = Box::into_raw(bytes) as *const u8;
+	}
 
-		Self { ptr, len, is_num }
+	fn set_params(&mut self, params: Vec<C_Param>)
+	{
+		let params = params.into_boxed_slice();
+		self.len = params.len();
+		self.ptr = Box::into_raw(params) as *const u8;
 	}
 
-	#[inline]
-	pub fn default() -> Self
+	/// Gibt den von [`C_Param::new`] angelegten Speicher rekursiv frei.
+	/// Parameter aus Lua gehören Lua und dürfen nicht freigegeben werden.
+	unsafe fn free(&mut self)
 	{
-		Self { ptr: ptr::null(), len: 0, is_num: false }
+		if self.ptr.is_null() { return; }
+		match self.kind
+		{
+			PARAM_STRING | PARAM_COMPONENT => {
+				drop(Box::from_raw(ptr::slice_from_raw_parts_mut(self.ptr as *mut u8, self.len)));
+			},
+			PARAM_ARRAY | PARAM_STRUCT => {
+				let mut params = Box::from_raw(ptr::slice_from_raw_parts_mut(self.ptr as *mut C_Param, self.len));
+				params.iter_mut().for_each(|p| p.free());
+			},
+			_ => {}
+		}
+		*self = Self::default();
 	}
 
-	pub fn to_string(self) -> String
+	/// Kopiert den Parameter in einen [`Value`]. Der Speicher des Parameters wird nicht übernommen.
+	pub unsafe fn value(&self) -> Value
 	{
-		unsafe { String::from_raw_parts(self.ptr as *mut u8, self.len, self.len) }
+		match self.kind
+		{
+			PARAM_BOOL => Value::Bool(self.int != 0),
+			PARAM_INT => Value::Int(self.int),
+			PARAM_FLOAT => Value::Float(self.float),
+			PARAM_STRING => Value::String(self.string()),
+			PARAM_COMPONENT => Value::Component(self.string()),
+			PARAM_ARRAY => Value::Array(self.params().iter().map(|p| p.value()).collect()),
+			PARAM_STRUCT => Value::Struct(self.params().chunks_exact(2)
+				.map(|kv| (kv[0].string(), kv[1].value()))
+				.collect()),
+			_ => Value::Nil,
+		}
 	}
 
 	#[inline]
-	pub fn as_str(self) -> &'static str
+	unsafe fn string(&self) -> String
 	{
-		std::str::from_utf8(self.into()).unwrap()
+		if self.ptr.is_null() { return String::new(); }
+		String::from_utf8_lossy(slice::from_raw_parts(self.ptr, self.len)).into_owned()
 	}
-}
 
-impl Into<&[u8]> for C_Param
-{
 	#[inline]
-	fn into(self) -> &'static [u8]
+	unsafe fn params(&self) -> &[C_Param]
 	{
-		unsafe { slice::from_raw_parts(self.ptr, self.len) }
-    }
+		if self.ptr.is_null() { return &[]; }
+		slice::from_raw_parts(self.ptr as *const C_Param, self.len)
+	}
 }
 
-
+/// Signal mit beliebig vielen Argumenten.
+/// Die Argumente und ihre Strings und Arrays gehören dem Signal und werden mit ihm freigegeben.
+/// Über FFI übergebene Signale müssen mit `free_signal` zurückgegeben werden.
 #[repr(C)]
 pub struct Signal
 {
 	pub eventType: *const u8,
 	pub component: UID,
 	pub len: usize,
-	pub args: [C_Param; 8],
+	pub args: *mut C_Param,
 }
 
 unsafe impl Send for Signal {}
@@ -188,35 +439,22 @@ unsafe impl Sync for Signal {}
 impl Signal
 {
 	pub fn numArgs<T>(eventType: &'static str, component: UID, vec: Vec<T>) -> Self
-	where T: ToString
+	where T: Into<Value>
 	{
-		let mut args = [C_Param::default(); 8];
-		for i in 0..vec.len().min(8)
-		{
-			args[i] = C_Param::from(&vec[i], true);
-		}
-
-		Self
-		{
-			eventType: eventType.as_ptr(),
-			component,
-			len: vec.len(),
-			args: args
-		}
+		Self::new(eventType, component, vec.into_iter().map(Into::into).collect())
 	}
 
-	pub fn raw(eventType: &'static str, component: UID, params: Vec<C_Param>) -> Self
+	pub fn new(eventType: &'static str, component: UID, values: Vec<Value>) -> Self
 	{
-		let mut args = [C_Param::default(); 8];
-		let len = params.len().min(8);
-		args[0..len].copy_from_slice(&params[0..len]);
+		let args: Box<[C_Param]> = values.iter().map(C_Param::new).collect();
+		let len = args.len();
 
 		Self
 		{
 			eventType: eventType.as_ptr(),
 			component,
-			len: params.len(),
-			args: args
+			len,
+			args: Box::into_raw(args) as *mut C_Param
 		}
 	}
 
@@ -227,7 +465,7 @@ impl Signal
 			eventType: ptr::null(),
 			component: UID::default(),
 			len: 0,
-			args: [C_Param::default(); 8]
+			args: ptr::null_mut()
 		}
 	}
 	
@@ -238,14 +476,472 @@ impl Signal
 			eventType: eventType.as_ptr(),
 			component: comp,
 			len: 0,
-			args: [C_Param::default(); 8]
+			args: ptr::null_mut()
 		}
 	}
 
+	/// Name des Signals ohne Nullterminierung.
 	pub fn name(&self) -> &'static str
 	{
 		if self.eventType.is_null() { panic!("invalid event!") }
-		let ptr = self.eventType as *const &str;
-		unsafe { ptr.read() }
+		unsafe { CStr::from_ptr(self.eventType as *const c_char).to_str().unwrap_or_default() }
 	}
-}
\ No newline at end of file
+
+	/// Kopiert die Argumente.
+	pub fn values(&self) -> Vec<Value>
+	{
+		if self.args.is_null() { return Vec::new(); }
+		unsafe { slice::from_raw_parts(self.args, self.len).iter().map(|p| p.value()).collect() }
+	}
+}
+
+impl Clone for Signal
+{
+	fn clone(&self) -> Self
+	{
+		let mut signal = Signal::new("\0", self.component, self.values());
+		signal.eventType = self.eventType;
+		signal
+	}
+}
+
+impl Drop for Signal
+{
+	fn drop(&mut self)
+	{
+		if !self.args.is_null()
+		{
+			unsafe
+			{
+				let mut args = Box::from_raw(ptr::slice_from_raw_parts_mut(self.args, self.len));
+				args.iter_mut().for_each(|p| p.free());
+			}
+			self.args = ptr::null_mut();
+		}
+	}
+}
+
+/// Parameter eines Signals, über Index oder FIN Namen.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParamKey
+{
+	Index(usize),
+	Name(String),
+}
+
+/// Filter für Signale wie event.filter in FIN.
+/// Ein Match passt, wenn Name, Sender und alle Werte passen. Leere Listen passen auf alles.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EventFilter
+{
+	Match
+	{
+		events: Vec<String>,
+		senders: Vec<String>,
+		values: Vec<(ParamKey, Value)>,
+	},
+	And(Box<EventFilter>, Box<EventFilter>),
+	Or(Box<EventFilter>, Box<EventFilter>),
+	Not(Box<EventFilter>),
+}
+
+impl Default for EventFilter
+{
+	fn default() -> Self
+	{
+		EventFilter::Match{ events: Vec::new(), senders: Vec::new(), values: Vec::new() }
+	}
+}
+
+impl EventFilter
+{
+	/// Erstellt einen Filter aus der Lua Tabelle `{event=..., sender=..., values={...}}`.
+	/// event und sender können einzelne Werte oder Arrays sein.
+	/// Schlüssel in values sind FIN Parameternamen oder 1-basierte Indizes.
+	/// Indizes beziehen sich auf die Argumente des Signals, nicht auf die von freen.lua
+	/// zusammengefassten, wie die Position bei Mausereignissen der GPU T2.
+	pub fn from_spec(spec: &Value) -> Result<Self, String>
+	{
+		let fields = match spec
+		{
+			Value::Struct(fields) => fields.as_slice(),
+			Value::Array(items) if items.is_empty() => &[],
+			_ => return Err("Filter must be a table".to_string()),
+		};
+
+		let mut filter = EventFilter::default();
+		if let EventFilter::Match{ events, senders, values } = &mut filter
+		{
+			for (key, value) in fields
+			{
+				match key.as_str()
+				{
+					"event" => *events = strings(value, "event")?,
+					"sender" => *senders = strings(value, "sender")?,
+					"values" => *values = param_values(value)?,
+					_ => return Err(format!("Unknown filter field {}", key)),
+				}
+			}
+		}
+		Ok(filter)
+	}
+
+	pub fn and(self, other: EventFilter) -> Self
+	{
+		EventFilter::And(Box::new(self), Box::new(other))
+	}
+
+	pub fn or(self, other: EventFilter) -> Self
+	{
+		EventFilter::Or(Box::new(self), Box::new(other))
+	}
+
+	pub fn negate(self) -> Self
+	{
+		EventFilter::Not(Box::new(self))
+	}
+
+	pub fn matches(&self, signal: &Signal) -> bool
+	{
+		if signal.eventType.is_null() { return false; }
+		self.matches_parts(signal.name(), &signal.component, &signal.values())
+	}
+
+	fn matches_parts(&self, name: &str, sender: &[u8], args: &[Value]) -> bool
+	{
+		match self
+		{
+			EventFilter::Match{ events, senders, values } => {
+				(events.is_empty() || events.iter().any(|e| e == name))
+				&& (senders.is_empty() || senders.iter().any(|s| s.as_bytes() == sender))
+				&& values.iter().all(|(key, expected)| {
+					let idx = match key
+					{
+						ParamKey::Index(i) => Some(*i),
+						ParamKey::Name(n) => param_index(name, n),
+					};
+					idx.and_then(|i| args.get(i)).is_some_and(|v| value_equals(v, expected))
+				})
+			},
+			EventFilter::And(a, b) => a.matches_parts(name, sender, args) && b.matches_parts(name, sender, args),
+			EventFilter::Or(a, b) => a.matches_parts(name, sender, args) || b.matches_parts(name, sender, args),
+			EventFilter::Not(a) => !a.matches_parts(name, sender, args),
+		}
+	}
+}
+
+/// Namen der Signal Parameter wie in FIN.
+fn param_index(event: &str, name: &str) -> Option<usize>
+{
+	let names: &[&str] = match event
+	{
+		"OnMouseDown" | "OnMouseUp" | "OnMouseMove" => &["x", "y", "btn"],
+		"OnMouseWheel" => &["x", "y", "delta", "btn"],
+		"OnKeyDown" | "OnKeyUp" => &["c", "code", "btn"],
+		"OnKeyChar" => &["c", "btn"],
+		"NetworkMessage" => &["sender", "port"],
+		"FileSystemUpdate" => &["type", "from", "to"],
+		_ => &[],
+	};
+	names.iter().position(|n| *n == name)
+}
+
+/// Lua kennt nur Zahlen, daher werden Int und Float numerisch verglichen.
+fn value_equals(a: &Value, b: &Value) -> bool
+{
+	match (a, b)
+	{
+		(Value::Int(i), Value::Float(f)) | (Value::Float(f), Value::Int(i)) => *i as f64 == *f,
+		_ => a == b,
+	}
+}
+
+/// Einzelner String oder Array aus Strings. Komponenten werden über ihre ID angegeben.
+fn strings(value: &Value, field: &str) -> Result<Vec<String>, String>
+{
+	match value
+	{
+		Value::String(s) | Value::Component(s) => Ok(vec![s.clone()]),
+		Value::Array(items) => items.iter().map(|v| match v
+		{
+			Value::String(s) | Value::Component(s) => Ok(s.clone()),
+			_ => Err(format!("Invalid filter {}", field)),
+		}).collect(),
+		_ => Err(format!("Invalid filter {}", field)),
+	}
+}
+
+fn param_values(value: &Value) -> Result<Vec<(ParamKey, Value)>, String>
+{
+	match value
+	{
+		// Nil Einträge in Arrays passen auf jeden Wert.
+		Value::Array(items) => Ok(items.iter().enumerate()
+			.filter(|(_, v)| **v != Value::Nil)
+			.map(|(i, v)| (ParamKey::Index(i), v.clone()))
+			.collect()),
+		Value::Struct(fields) => Ok(fields.iter().map(|(k, v)| {
+			let key = match k.parse::<usize>()
+			{
+				Ok(i) if i > 0 => ParamKey::Index(i - 1),
+				_ => ParamKey::Name(k.clone()),
+			};
+			(key, v.clone())
+		}).collect()),
+		_ => Err("Invalid filter values".to_string()),
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use std::alloc::{GlobalAlloc, Layout, System};
+	use std::cell::Cell;
+	use std::ffi::CStr;
+
+	/// Zählt die belegten Bytes pro Thread, damit parallel laufende Tests sich nicht stören.
+	/// Die Tests laufen auch unter Miri, das Lecks zusätzlich selbst meldet.
+	struct LeakCounter;
+
+	thread_local! {
+		static LIVE_BYTES: Cell<isize> = const { Cell::new(0) };
+	}
+
+	unsafe impl GlobalAlloc for LeakCounter
+	{
+		unsafe fn alloc(&self, layout: Layout) -> *mut u8
+		{
+			let _ = LIVE_BYTES.try_with(|b| b.set(b.get() + layout.size() as isize));
+			System.alloc(layout)
+		}
+
+		unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout)
+		{
+			let _ = LIVE_BYTES.try_with(|b| b.set(b.get() - layout.size() as isize));
+			System.dealloc(ptr, layout)
+		}
+	}
+
+	#[global_allocator]
+	static ALLOCATOR: LeakCounter = LeakCounter;
+
+	fn live_bytes() -> isize
+	{
+		LIVE_BYTES.with(|b| b.get())
+	}
+
+	fn sample_values() -> Vec<Value>
+	{
+		vec![
+			Value::Nil,
+			Value::Bool(true),
+			Value::Int(-7),
+			Value::Float(1.5),
+			Value::from("Hällo"),
+			Value::Array(vec![Value::Int(1), Value::from("a"), Value::Array(vec![Value::from("b")])]),
+			Value::Struct(vec![("r".to_string(), Value::Float(1.0)), ("name".to_string(), Value::from("x"))]),
+			Value::Component("4D92F19549A229990C9F5CBEFC69D414".to_string()),
+		]
+	}
+
+	#[test]
+	fn signal_frees_all_arguments()
+	{
+		let values = sample_values();
+		let before = live_bytes();
+		let signal = Signal::new("Test\0", UID::default(), values.clone());
+		assert_eq!(signal.len, values.len());
+		drop(signal);
+		assert_eq!(live_bytes(), before);
+	}
+
+	#[test]
+	fn params_round_trip_values()
+	{
+		let values = sample_values();
+		let before = live_bytes();
+		for value in &values
+		{
+			let mut param = C_Param::new(value);
+			assert_eq!(unsafe { param.value() }, *value);
+			unsafe { param.free() };
+		}
+		assert_eq!(live_bytes(), before);
+	}
+
+	#[test]
+	fn foreign_params_are_copied()
+	{
+		// Parameter aus Lua zeigen auf Speicher, der Lua gehört.
+		let text = String::from("Lua String");
+		let param = C_Param{ kind: PARAM_STRING, int: 0, float: 0.0, ptr: text.as_ptr(), len: text.len() };
+		let value = unsafe { param.value() };
+		assert_eq!(value, Value::String(text.clone()));
+		assert_eq!(text, "Lua String");
+	}
+
+	#[test]
+	fn array_frees_strings()
+	{
+		let before = live_bytes();
+		let array = C_Array::new(&["eins", "zw\0ei"]);
+		assert_eq!(array.len, 2);
+		let second = unsafe { CStr::from_ptr(*array.ptr.offset(1) as *const c_char) };
+		assert_eq!(second.to_str().unwrap(), "zwei");
+		unsafe { array.free() };
+		assert_eq!(live_bytes(), before);
+	}
+
+	#[test]
+	fn ignored_components_are_dropped()
+	{
+		let mut handler = EventHandler::new();
+		let owner = *b"0123456789ABCDEF";
+		// Komponenten sind mit dem ersten Emitter angemeldet.
+		let mut emitter = handler.new_emitter(owner);
+		assert_eq!(handler.listening(), vec![owner]);
+		emitter.send(Signal::noArgs("Test\0", owner));
+		assert_eq!(handler.poll(None).unwrap().component, owner);
+
+		handler.ignore(owner);
+		emitter.send(Signal::noArgs("Test\0", owner));
+		assert!(handler.poll(None).unwrap().eventType.is_null());
+
+		handler.listen(owner);
+		assert_eq!(handler.listening(), vec![owner]);
+		emitter.send(Signal::noArgs("Test\0", owner));
+		assert_eq!(handler.poll(None).unwrap().component, owner);
+
+		// Ein neuer Emitter, etwa nach bindScreen, meldet eine ignorierte Komponente nicht wieder an.
+		handler.ignore(owner);
+		let mut emitter = handler.new_emitter(owner);
+		assert!(handler.listening().is_empty());
+		emitter.send(Signal::noArgs("Test\0", owner));
+		assert!(handler.poll(None).unwrap().eventType.is_null());
+
+		// Signale ohne Komponente werden immer zugestellt.
+		handler.ignore_all();
+		handler.new_emitter(UID::default()).send(Signal::noArgs("Test\0", UID::default()));
+		assert!(handler.listening().is_empty());
+		assert!(!handler.poll(None).unwrap().eventType.is_null());
+	}
+
+	#[test]
+	fn unread_signals_are_freed()
+	{
+		let before = live_bytes();
+		{
+			let mut handler = EventHandler::new();
+			let mut emitter = handler.new_emitter(UID::default());
+			emitter.send(Signal::new("Test\0", UID::default(), sample_values()));
+			emitter.send(Signal::new("Test\0", UID::default(), sample_values()));
+			drop(handler.poll(None));
+		}
+		assert_eq!(live_bytes(), before);
+	}
+
+	fn message(port: i64, data: &str) -> Signal
+	{
+		Signal::new("NetworkMessage\0", UID::default(), vec![Value::from("0123456789ABCDEF"), Value::Int(port), Value::from(data)])
+	}
+
+	#[test]
+	fn filters_match_names_and_values()
+	{
+		let spec = Value::Struct(vec![
+			("event".to_string(), Value::from("NetworkMessage")),
+			("values".to_string(), Value::Struct(vec![
+				("port".to_string(), Value::Float(42.0)),
+				("3".to_string(), Value::from("ping")),
+			])),
+		]);
+		let filter = EventFilter::from_spec(&spec).unwrap();
+		assert!(filter.matches(&message(42, "ping")));
+		assert!(!filter.matches(&message(42, "pong")));
+		assert!(!filter.matches(&message(7, "ping")));
+		assert!(!filter.clone().negate().matches(&message(42, "ping")));
+
+		let other = EventFilter::from_spec(&Value::Struct(vec![("values".to_string(), Value::Array(vec![Value::Nil, Value::Int(7)]))])).unwrap();
+		assert!(filter.clone().or(other.clone()).matches(&message(7, "pong")));
+		assert!(!filter.and(other).matches(&message(7, "ping")));
+		assert!(EventFilter::default().matches(&Signal::noArgs("Test\0", UID::default())));
+		assert!(!EventFilter::default().matches(&Signal::default()));
+		assert!(EventFilter::from_spec(&Value::Struct(vec![("name".to_string(), Value::Nil)])).is_err());
+	}
+
+	#[test]
+	fn filtered_polls_keep_skipped_signals()
+	{
+		let mut handler = EventHandler::new();
+		let mut emitter = handler.new_emitter(UID::default());
+		emitter.send(message(1, "a"));
+		emitter.send(message(2, "b"));
+		emitter.send(message(3, "c"));
+
+		let filter = EventFilter::from_spec(&Value::Struct(vec![("values".to_string(), Value::Struct(vec![("port".to_string(), Value::Int(2))]))])).unwrap();
+		assert_eq!(handler.poll_filtered(&filter, None).values()[2], Value::from("b"));
+		assert!(handler.poll_filtered(&filter, None).eventType.is_null());
+		assert_eq!(handler.poll(None).unwrap().values()[2], Value::from("a"));
+		assert_eq!(handler.poll(None).unwrap().values()[2], Value::from("c"));
+		assert!(handler.poll(None).unwrap().eventType.is_null());
+	}
+
+	#[test]
+	fn pending_signals_are_bounded()
+	{
+		let before = live_bytes();
+		{
+			let mut handler = EventHandler::new();
+			let mut emitter = handler.new_emitter(UID::default());
+			let queue = handler.create_queue(EventFilter::default());
+			let count = MAX_PENDING_SIGNALS as i64 + 10;
+			for port in 0..count
+			{
+				emitter.send(message(port, "x"));
+			}
+
+			// Kein Signal passt, alle werden zurückgehalten und die ältesten verworfen.
+			let none = EventFilter::default().negate();
+			assert!(handler.poll_filtered(&none, None).eventType.is_null());
+			assert_eq!(handler.pending.len(), MAX_PENDING_SIGNALS);
+			assert_eq!(handler.poll(None).unwrap().values()[1], Value::Int(10));
+			assert_eq!(handler.poll_queue(&queue, None, None).values()[1], Value::Int(10));
+			assert_eq!(queue.lock().unwrap().signals.len(), MAX_PENDING_SIGNALS - 1);
+		}
+		assert_eq!(live_bytes(), before);
+	}
+
+	#[test]
+	fn queues_collect_copies()
+	{
+		let before = live_bytes();
+		{
+			let mut handler = EventHandler::new();
+			let mut emitter = handler.new_emitter(UID::default());
+			emitter.send(message(1, "before"));
+			let queue = handler.create_queue(EventFilter::default());
+			let pings = EventFilter::from_spec(&Value::Struct(vec![("values".to_string(), Value::Array(vec![Value::Nil, Value::Nil, Value::from("ping")]))])).unwrap();
+			let ports = handler.create_queue(pings.clone());
+			emitter.send(message(2, "ping"));
+			emitter.send(message(3, "pong"));
+
+			assert_eq!(handler.poll_queue(&queue, Some(&pings), None).values()[1], Value::Int(2));
+			assert_eq!(handler.poll_queue(&queue, None, None).values()[1], Value::Int(3));
+			assert!(handler.poll_queue(&queue, None, None).eventType.is_null());
+			assert_eq!(handler.poll_queue(&ports, None, None).values()[1], Value::Int(2));
+			assert!(handler.poll_queue(&ports, None, None).eventType.is_null());
+
+			// Signale aus Queues bleiben für poll erhalten.
+			assert_eq!(handler.poll(None).unwrap().values()[1], Value::Int(1));
+			assert_eq!(handler.poll(None).unwrap().values()[1], Value::Int(2));
+			assert_eq!(handler.poll(None).unwrap().values()[1], Value::Int(3));
+
+			drop(ports);
+			emitter.send(message(4, "ping"));
+			drop(handler.poll(None));
+			assert_eq!(handler.queues.len(), 1);
+		}
+		assert_eq!(live_bytes(), before);
+	}
+}
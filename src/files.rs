@@ -1,6 +1,6 @@
 use std::{path::{Path, PathBuf}, fs};
 
-use crate::{events::{EventEmitter, Signal, C_Param}, component::{UID, Component}};
+use crate::{events::{EventEmitter, Signal, Value}, component::{UID, Component}};
 
 const EVENT_FILESYSTEM_UPDATE: &str = "FileSystemUpdate\0";
 
@@ -65,13 +65,13 @@ impl Filesystem
 			//println!("{:?}", [&self.root, id]);
 			let device = Device{
 				id: id.to_owned(),
-				path: path,
+				path,
 				depth: mount_path.components().count(),
 				mount: mount_path.clone()
 			};
 			self.mounts.push(device);
 			// Stelle sicher, dass tiefere Mountpoints vor höheren abgesucht werden.
-			self.mounts.sort_by(|a, b| b.depth.cmp(&a.depth));
+			self.mounts.sort_by_key(|m| std::cmp::Reverse(m.depth));
 			self.fire_filesystem_update(0, Some(&mount_path), None);
 			true
 		}
@@ -155,10 +155,9 @@ impl Filesystem
 	{
 		if let Some(from_path) = self.resolve(from)
 		{
-			match self.resolve(to)
+			if let Some(to_path) = self.resolve(to)
 			{
-				Some(to_path) => return fs::rename(from_path, to_path).is_ok(),
-				None => {}
+				return fs::rename(from_path, to_path).is_ok();
 			}
 		}
 		false
@@ -167,18 +166,15 @@ impl Filesystem
 	pub fn childs(&self, path_name: &str) -> Vec<String>
 	{
 		let mut items = Vec::<String>::new();
-		match self.resolve(path_name)
+		if let Some(path) = self.resolve(path_name)
 		{
-			Some(path) => {
-				for item in fs::read_dir(path).expect("Error while reading directory.")
-				{
-					let entry = item.expect("Error while reading file.");
-					println!("resolved {:?}", entry);
-					let file_path = path_name.to_owned() + "/" + entry.file_name().to_str().unwrap() + "\0";
-					items.push(file_path);
-				}
-			},
-			None => {}
+			for item in fs::read_dir(path).expect("Error while reading directory.")
+			{
+				let entry = item.expect("Error while reading file.");
+				println!("resolved {:?}", entry);
+				let file_path = path_name.to_owned() + "/" + entry.file_name().to_str().unwrap();
+				items.push(file_path);
+			}
 		}
 		items
 	}
@@ -195,7 +191,7 @@ impl Filesystem
 		let path = rel_path(path_name);
 		for mount in &self.mounts
 		{
-			if let Some(rel_path) = path.strip_prefix(&mount.mount).ok()
+			if let Ok(rel_path) = path.strip_prefix(&mount.mount)
 			{
 				let full_path: PathBuf = [&mount.path, &PathBuf::from(rel_path)].iter().collect();
 				// Teste, ob wir noch innerhalb des Dateisystems sind.
@@ -207,7 +203,7 @@ impl Filesystem
 		None
 	}
 
-	fn device_path<'a>(&self, path: &'a PathBuf) -> Option<&'a Path>
+	fn device_path<'a>(&self, path: &'a Path) -> Option<&'a Path>
 	{
 		path.strip_prefix(&self.name).ok()
 	}
@@ -216,12 +212,12 @@ impl Filesystem
 	{
 		if let Some(e) = self.emitter.as_mut()
 		{
-			let mut params = Vec::<C_Param>::new();
-			params.push(C_Param::from(&event_type, true));
-			if let Some(p) = path { params.push(c_param(p)); }
-			if let Some(p) = new_path { params.push(c_param(p)); }
+			let mut params = Vec::<Value>::new();
+			params.push(Value::from(event_type));
+			if let Some(p) = path { params.push(path_value(p)); }
+			if let Some(p) = new_path { params.push(path_value(p)); }
 
-			e.send(Signal::raw(EVENT_FILESYSTEM_UPDATE, e.owner(), params));
+			e.send(Signal::new(EVENT_FILESYSTEM_UPDATE, e.owner(), params));
 		}
 	}
 }
@@ -233,7 +229,7 @@ fn rel_path(str: &str) -> PathBuf
 }
 
 #[inline]
-fn c_param(path: &PathBuf) -> C_Param
+fn path_value(path: &Path) -> Value
 {
-	C_Param::from_str(&path.to_str().unwrap())
+	Value::from(path.to_str().unwrap())
 }
\ No newline at end of file
@@ -1,4 +1,6 @@
 #![allow(non_snake_case)]
+// Die Sicherheitsbedingungen aller FFI Funktionen stehen gemeinsam über handle().
+#![allow(clippy::missing_safety_doc)]
 
 mod component;
 use crate::component::*;
@@ -15,25 +17,27 @@ use crate::network::*;
 mod screens;
 use crate::screens::*;
 use screens::screen::*;
+use screens::canvas::*;
+use screens::fonts::set_font_paths;
+use screens::headless::*;
+use screens::snapshot::*;
+use screens::storage::*;
 
 use core::time;
 use std::ffi::CStr;
+use std::path::Path;
 use std::os::raw::c_char;
+use std::sync::{Arc, Mutex};
 
 
-unsafe fn param_to_vec(data: *const C_Param, len: usize) -> Vec<String>
+unsafe fn param_to_vec(data: *const C_Param, len: usize) -> Vec<Value>
 {
-	let mut vec = Vec::<String>::with_capacity(len);
-	let mut ptr = data;
-	for _i in 0..len
-	{
-		let slice = ptr.read();
-		ptr = ptr.offset(1);
-		vec.push(slice.to_string());
-	}
-	vec
+	if data.is_null() { return Vec::new(); }
+	std::slice::from_raw_parts(data, len).iter().map(|p| p.value()).collect()
 }
 
+// Alle FFI Funktionen erwarten Handles, die von der passenden Erstellungsfunktion stammen
+// und noch nicht freigegeben wurden, sowie nullterminierte UTF-8 Strings.
 #[inline]
 unsafe fn handle<T>(h: *mut T) -> &'static mut T
 {
@@ -51,7 +55,7 @@ unsafe fn c2str(cstr: *const c_char) -> &'static str
 #[inline]
 unsafe fn c2char(ch: *const c_char) -> char
 {
-	c2str(ch).chars().nth(0).expect("Ungültiges Zeichen")
+	c2str(ch).chars().next().expect("Ungültiges Zeichen")
 }
 
 // Event Testing Funktion
@@ -63,25 +67,18 @@ pub unsafe extern "C" fn event_test(handler: *mut EventHandler, len: usize, data
 	let vector = param_to_vec(data, len);
 
 	//let mut ptr = data;
-	for s in vector
+	for v in vector
 	{
 		//let slice = ptr.read();
 		//let s = std::str::from_utf8(slice.into()).unwrap();
 		//ptr = ptr.offset(1);
-		println!("String: {}", s);
+		println!("Wert: {:?}", v);
 	}
 
-	let arg1: C_Param;
-	let arg2: C_Param;
-	{
-		let str = "Ausgabe Arg 1";
-		arg1 = C_Param::from_str(str);
-
-		let str = "Ausgabe Argument 2";
-		arg2 = C_Param::from_str(str);
-	}
+	let arg1 = Value::from("Ausgabe Arg 1");
+	let arg2 = Value::from("Ausgabe Argument 2");
 
-	let signal = Signal::raw("Text Event Type\0", generateUID(), vec![arg1, arg2]);
+	let signal = Signal::new("Text Event Type\0", generateUID(), vec![arg1, arg2]);
 	handle(handler).sender().lock().unwrap().send(signal).ok();
 
 	//let str = "Ausgabe Text\0".to_string();
@@ -126,38 +123,161 @@ pub unsafe extern "C" fn start_listen(hptr: *mut EventHandler, sptr: *mut Screen
 	screen.listen(Some(emitter));
 }
 
+/// Liest eine Komponenten ID aus Lua. Nur native Komponenten haben IDs der Länge UID_SIZE.
+#[inline]
+unsafe fn c2uid(cstr: *const c_char) -> Option<UID>
+{
+	c2str(cstr).as_bytes().try_into().ok()
+}
+
 #[no_mangle]
-pub unsafe extern "C" fn pull(ptr: *mut EventHandler, t: f32) -> Signal
+pub unsafe extern "C" fn event_listen(ptr: *mut EventHandler, id: *const c_char) -> bool
+{
+	match c2uid(id)
+	{
+		Some(uid) => { handle(ptr).listen(uid); true },
+		None => false
+	}
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn event_ignore(ptr: *mut EventHandler, id: *const c_char) -> bool
+{
+	match c2uid(id)
+	{
+		Some(uid) => { handle(ptr).ignore(uid); true },
+		None => false
+	}
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn event_ignore_all(ptr: *mut EventHandler)
+{
+	handle(ptr).ignore_all();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn event_listening(ptr: *mut EventHandler) -> C_Array
+{
+	let ids: Vec<String> = handle(ptr).listening().iter()
+		.map(|uid| String::from_utf8_lossy(uid).into_owned())
+		.collect();
+	C_Array::new(&ids)
+}
+
+/// Lua Timeout in Sekunden. 0 oder weniger wartet nicht.
+#[inline]
+fn timeout(t: f32) -> Option<time::Duration>
 {
-	let timeout = if t > 0.0 { Some(time::Duration::from_millis((t * 1000.0) as u64))} else {None};
+	if t > 0.0 { Some(time::Duration::from_millis((t * 1000.0) as u64)) } else { None }
+}
 
+#[no_mangle]
+pub unsafe extern "C" fn pull(ptr: *mut EventHandler, t: f32) -> Signal
+{
 	let handler = handle(ptr);
-	match handler.poll(timeout)
+	match handler.poll(timeout(t))
 	{
-		Ok(sig) => { return sig; }
+		Ok(sig) => sig,
 
 		Err(e) => {
 			eprintln!("Event Error {}", e);
-			return Signal::default();
+			Signal::default()
 		}
 	}
 }
 
 #[no_mangle]
-pub extern "C" fn create_screen(fontsize: u32, handler: *mut EventHandler) -> UIDHandle<ScreenComponent>
+pub unsafe extern "C" fn pull_filtered(ptr: *mut EventHandler, filter: *mut EventFilter, t: f32) -> Signal
+{
+	handle(ptr).poll_filtered(handle(filter), timeout(t))
+}
+
+/// Erstellt einen Filter aus einer Lua Tabelle. Gibt bei ungültigen Angaben null zurück.
+#[no_mangle]
+pub unsafe extern "C" fn filter_new(spec: C_Param) -> *mut EventFilter
+{
+	match EventFilter::from_spec(&spec.value())
+	{
+		Ok(filter) => Box::into_raw(Box::new(filter)),
+		Err(e) => {
+			eprintln!("Event Error {}", e);
+			std::ptr::null_mut()
+		}
+	}
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn filter_and(a: *mut EventFilter, b: *mut EventFilter) -> *mut EventFilter
+{
+	Box::into_raw(Box::new(handle(a).clone().and(handle(b).clone())))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn filter_or(a: *mut EventFilter, b: *mut EventFilter) -> *mut EventFilter
+{
+	Box::into_raw(Box::new(handle(a).clone().or(handle(b).clone())))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn filter_not(a: *mut EventFilter) -> *mut EventFilter
+{
+	Box::into_raw(Box::new(handle(a).clone().negate()))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn filter_matches(filter: *mut EventFilter, signal: *const Signal) -> bool
+{
+	!signal.is_null() && handle(filter).matches(&*signal)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn filter_free(filter: *mut EventFilter)
+{
+	if !filter.is_null() { drop(Box::from_raw(filter)); }
+}
+
+/// Erstellt eine Queue. Ohne Filter werden alle Signale gesammelt.
+#[no_mangle]
+pub unsafe extern "C" fn queue_new(ptr: *mut EventHandler, filter: *mut EventFilter) -> *const Mutex<EventQueue>
+{
+	let filter = if filter.is_null() { EventFilter::default() } else { handle(filter).clone() };
+	Arc::into_raw(handle(ptr).create_queue(filter))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn queue_pull(ptr: *mut EventHandler, queue: *const Mutex<EventQueue>, filter: *mut EventFilter, t: f32) -> Signal
+{
+	assert!(!queue.is_null(), "handle is null");
+	let filter = if filter.is_null() { None } else { Some(&*filter) };
+	handle(ptr).poll_queue(&*queue, filter, timeout(t))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn queue_free(queue: *const Mutex<EventQueue>)
+{
+	if !queue.is_null() { drop(Arc::from_raw(queue)); }
+}
+
+/// Gibt die Argumente eines mit `pull` gelesenen Signals frei.
+#[no_mangle]
+pub unsafe extern "C" fn free_signal(signal: Signal)
+{
+	drop(signal);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn create_screen(fontsize: u32, handler: *mut EventHandler) -> UIDHandle<ScreenComponent>
 {
 	assert!(fontsize > 1);
 	let mut screen = ScreenComponent::new(fontsize);
-	unsafe
+	if handler.is_null()
 	{
-		if handler.is_null()
-		{
-			screen.listen(None);
-		}
-		else
-		{
-			screen.listen(Some((*handler).new_emitter(screen.uid())));
-		}
+		screen.listen(None);
+	}
+	else
+	{
+		screen.listen(Some((*handler).new_emitter(screen.uid())));
 	}
 	UIDHandle::new(screen)
 }
@@ -178,7 +298,7 @@ pub unsafe extern "C" fn bind_screen(gPtr: *mut GraphicHandle, sPtr: *mut Screen
 	}
 	else
 	{
-		let screen = *Box::from_raw(sPtr);
+		let screen = handle(sPtr).share();
 		handle(gPtr).bind_screen(Some(screen));
 	}
 }
@@ -186,7 +306,18 @@ pub unsafe extern "C" fn bind_screen(gPtr: *mut GraphicHandle, sPtr: *mut Screen
 #[no_mangle]
 pub unsafe extern "C" fn destroy_screen(ptr: *mut ScreenComponent)
 {
-	if !ptr.is_null() { Box::from_raw(ptr); }
+	if !ptr.is_null()
+	{
+		let screen = Box::from_raw(ptr);
+		screen.close();
+		drop(screen);
+	}
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn set_backend(ptr: *mut ScreenComponent, backend: u8)
+{
+	handle(ptr).set_backend(Backend::from_u8(backend));
 }
 
 #[no_mangle]
@@ -215,12 +346,14 @@ pub unsafe extern "C" fn write_text(ptr: *mut GraphicHandle, x: i32, y: i32, cst
 {
 	let handle = handle(ptr);
 	handle.exec(|buffer| {
-		buffer.writeText(x, y, &c2str(cstr), handle.fg, handle.bg);
+		buffer.writeText(x, y, c2str(cstr), handle.fg, handle.bg);
 	});
 }
 
+// Exportierte Symbole dürfen nicht wie libc Funktionen heißen (write, mount, unmount),
+// sonst ersetzen sie diese im ganzen Prozess und der Host stürzt beim nächsten Aufruf ab.
 #[no_mangle]
-pub unsafe extern "C" fn write(ptr: *mut GraphicHandle, x: i32, y: i32, ch: *const c_char)
+pub unsafe extern "C" fn write_char(ptr: *mut GraphicHandle, x: i32, y: i32, ch: *const c_char)
 {
 	let handle = handle(ptr);
 	handle.exec(|buffer| {
@@ -234,6 +367,49 @@ pub unsafe extern "C" fn set_location(ptr: *mut ScreenComponent, x: i32, y: i32)
 	handle(ptr).screen_location(x, y);
 }
 
+/// Setzt Schriftart und Ausweichschriftarten für alle danach geöffneten Bildschirme.
+/// Eine leere Liste stellt die eingebettete Schriftart wieder her.
+#[no_mangle]
+pub unsafe extern "C" fn set_fonts(paths: *const *const c_char, len: usize)
+{
+	let paths: Vec<&str> = std::slice::from_raw_parts(paths, len).iter().map(|p| c2str(*p)).collect();
+	set_font_paths(&paths);
+}
+
+/// Ein negativer Index wählt den aktuellen Monitor.
+#[no_mangle]
+pub unsafe extern "C" fn set_monitor(ptr: *mut ScreenComponent, index: i32)
+{
+	handle(ptr).screen_monitor(usize::try_from(index).ok());
+}
+
+/// 0: Fenster, 1: randloses Vollbild, 2: exklusives Vollbild
+#[no_mangle]
+pub unsafe extern "C" fn set_fullscreen(ptr: *mut ScreenComponent, mode: u8)
+{
+	handle(ptr).screen_fullscreen(FullscreenMode::from_u8(mode));
+}
+
+/// Bei einem Fehler ist der Zeiger der Liste null.
+#[no_mangle]
+pub unsafe extern "C" fn get_monitors(ptr: *mut ScreenComponent) -> C_Monitors
+{
+	match handle(ptr).monitors()
+	{
+		Ok(monitors) => C_Monitors::from(monitors),
+		Err(e) => {
+			eprintln!("Screen Error {}", e);
+			C_Monitors::error()
+		}
+	}
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn free_monitors(monitors: C_Monitors)
+{
+	monitors.free();
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn set_size(ptr: *mut GraphicHandle, width: u32, height: u32)
 {
@@ -248,6 +424,32 @@ pub unsafe extern "C" fn flush(ptr: *mut GraphicHandle)
 	handle(ptr).flush();
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn dirty_count(ptr: *mut GraphicHandle) -> u32
+{
+	handle(ptr).dirty_count() as u32
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn screenshot(ptr: *mut GraphicHandle, cpath: *const c_char, fontsize: u32) -> bool
+{
+	assert!(fontsize > 1);
+	handle(ptr).screenshot(Path::new(c2str(cpath)), fontsize)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn start_recording(ptr: *mut GraphicHandle, cpath: *const c_char, fontsize: u32) -> bool
+{
+	assert!(fontsize > 1);
+	handle(ptr).start_recording(Path::new(c2str(cpath)), fontsize)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn stop_recording(ptr: *mut GraphicHandle) -> i32
+{
+	handle(ptr).stop_recording()
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn get_buffer(ptr: *mut GraphicHandle) -> *mut Buffer
 {
@@ -289,53 +491,256 @@ pub unsafe extern "C" fn buf_clone(ptr: *mut Buffer) -> *mut Buffer
 #[no_mangle]
 pub unsafe extern "C" fn buf_fill(ptr: *mut Buffer, x: i32, y: i32, w: i32, h: i32, ch: *const c_char, fg: Color, bg: Color)
 {
-	handle(ptr).fill(x, y, w, h, c2char(ch), fg.clone(), bg.clone());
+	handle(ptr).fill(x, y, w, h, c2char(ch), fg, bg);
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn buf_write(ptr: *mut Buffer, x: i32, y: i32, cstr: *const c_char, fg: Color, bg: Color)
 {
-	handle(ptr).writeText(x, y, c2str(cstr), fg.clone(), bg.clone());
+	handle(ptr).writeText(x, y, c2str(cstr), fg, bg);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn buf_set_raw(ptr: *mut Buffer, x: i32, y: i32, cstr: *const c_char, fg: *const Color, fg_len: usize, bg: *const Color, bg_len: usize) -> bool
+{
+	let fg = if fg_len == 0 { &[] } else { std::slice::from_raw_parts(fg, fg_len) };
+	let bg = if bg_len == 0 { &[] } else { std::slice::from_raw_parts(bg, bg_len) };
+	handle(ptr).setRaw(x, y, c2str(cstr), fg, bg)
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn buf_set(ptr: *mut Buffer, x: i32, y: i32, ch: *const c_char, fg: Color, bg: Color)
 {
-	handle(ptr).write(x, y, c2char(ch), fg.clone(), bg.clone());
+	handle(ptr).write(x, y, c2char(ch), fg, bg);
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn buf_get(ptr: *mut Buffer, x: u32, y: u32) -> BufferCell
 {
-	let buffer = handle(ptr);
-	let idx = x as usize + y as usize * buffer.width as usize;
+	handle(ptr).get_cell(x, y)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn buf_render(ptr: *mut Buffer, fontsize: u32) -> C_Image
+{
+	assert!(fontsize > 1);
+	match render_offscreen(handle(ptr), fontsize)
+	{
+		Ok(image) => image.into(),
+		Err(e) => {
+			eprintln!("Buffer Error {}", e);
+			C_Image::error()
+		}
+	}
+}
 
-	let mut char = [0; 4];
-	let len = buffer.chars[idx].encode_utf8(&mut char).len();
+#[no_mangle]
+pub unsafe extern "C" fn free_image(image: C_Image)
+{
+	image.free();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn free_array(array: C_Array)
+{
+	array.free();
+}
 
-	BufferCell{
-		char,
-		len,
-		fg: buffer.foreground[idx].clone(),
-		bg: buffer.background[idx].clone(),
+#[no_mangle]
+pub unsafe extern "C" fn buf_save(ptr: *mut Buffer, cpath: *const c_char) -> bool
+{
+	match save_buffer(handle(ptr), Path::new(c2str(cpath)))
+	{
+		Ok(_) => true,
+		Err(e) => {
+			eprintln!("Buffer Error {}", e);
+			false
+		}
 	}
 }
 
+/// Gibt bei Fehlern einen Null Zeiger zurück.
 #[no_mangle]
-pub extern "C" fn create_network(port_start: u16, buffer_size: usize, handler: *mut EventHandler) -> UIDHandle<NetworkComponent>
+pub unsafe extern "C" fn buf_load(cpath: *const c_char) -> *mut Buffer
 {
-	let mut network = NetworkComponent::new(port_start, buffer_size);
-	unsafe
+	match load_buffer(Path::new(c2str(cpath)))
 	{
-		if handler.is_null()
-		{
-			network.listen(None);
+		Ok(buffer) => Box::into_raw(Box::new(buffer)),
+		Err(e) => {
+			eprintln!("Buffer Error {}", e);
+			std::ptr::null_mut()
 		}
-		else
-		{
-			network.listen(Some((*handler).new_emitter(network.uid())));
+	}
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn buf_save_snapshot(ptr: *mut Buffer, cpath: *const c_char) -> bool
+{
+	match save_snapshot(handle(ptr), Path::new(c2str(cpath)))
+	{
+		Ok(_) => true,
+		Err(e) => {
+			eprintln!("Snapshot Error {}", e);
+			false
+		}
+	}
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn buf_compare_snapshot(ptr: *mut Buffer, cpath: *const c_char) -> C_Array
+{
+	C_Array::new(&compare_snapshot(handle(ptr), Path::new(c2str(cpath))))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn buf_save_image(ptr: *mut Buffer, cpath: *const c_char, fontsize: u32) -> bool
+{
+	assert!(fontsize > 1);
+	match save_reference_image(handle(ptr), Path::new(c2str(cpath)), fontsize)
+	{
+		Ok(_) => true,
+		Err(e) => {
+			eprintln!("Snapshot Error {}", e);
+			false
 		}
 	}
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn buf_compare_image(ptr: *mut Buffer, cpath: *const c_char, fontsize: u32, tolerance: u8) -> C_Array
+{
+	assert!(fontsize > 1);
+	C_Array::new(&compare_reference_image(handle(ptr), Path::new(c2str(cpath)), fontsize, tolerance))
+}
+
+#[no_mangle]
+pub extern "C" fn gpu2_handle(width: u32, height: u32) -> *mut CanvasHandle
+{
+	assert!(width > 0);
+	assert!(height > 0);
+	Box::into_raw(Box::new(CanvasHandle::new(width, height)))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn gpu2_bind_screen(gPtr: *mut CanvasHandle, sPtr: *mut ScreenComponent)
+{
+	if sPtr.is_null()
+	{
+		handle(gPtr).bind_screen(None);
+	}
+	else
+	{
+		let screen = handle(sPtr).share();
+		handle(gPtr).bind_screen(Some(screen));
+	}
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn gpu2_screen_size(ptr: *mut CanvasHandle) -> Vector2
+{
+	handle(ptr).size()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn gpu2_draw_rect(ptr: *mut CanvasHandle, position: Vector2, size: Vector2, color: Color, rotation: f32)
+{
+	handle(ptr).draw(DrawCall::Rect{position, size, color, rotation});
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn gpu2_draw_line(ptr: *mut CanvasHandle, points: *const Vector2, len: usize, thickness: f32, color: Color)
+{
+	// LuaJIT übergibt für leere Tabellen einen Nullzeiger.
+	if points.is_null() || len == 0 { return; }
+	let points = std::slice::from_raw_parts(points, len).to_vec();
+	handle(ptr).draw(DrawCall::Line{points, thickness, color});
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn gpu2_draw_box(ptr: *mut CanvasHandle, settings: BoxSettings)
+{
+	handle(ptr).draw(DrawCall::Box(settings));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn gpu2_draw_text(ptr: *mut CanvasHandle, position: Vector2, cstr: *const c_char, size: f32, color: Color, monospace: bool)
+{
+	let text = c2str(cstr).to_owned();
+	handle(ptr).draw(DrawCall::Text{position, text, size, color, monospace});
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn gpu2_draw_spline(ptr: *mut CanvasHandle, start: Vector2, start_direction: Vector2, end: Vector2, end_direction: Vector2, thickness: f32, color: Color)
+{
+	handle(ptr).draw(DrawCall::Spline{start, start_direction, end, end_direction, thickness, color});
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn gpu2_draw_bezier(ptr: *mut CanvasHandle, p0: Vector2, p1: Vector2, p2: Vector2, p3: Vector2, thickness: f32, color: Color)
+{
+	handle(ptr).draw(DrawCall::Bezier{p0, p1, p2, p3, thickness, color});
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn gpu2_push_transform(ptr: *mut CanvasHandle, translation: Vector2, rotation: f32, scale: Vector2)
+{
+	handle(ptr).push_transform(translation, rotation, scale);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn gpu2_push_layout(ptr: *mut CanvasHandle, offset: Vector2, size: Vector2, scale: f32)
+{
+	handle(ptr).push_layout(offset, size, scale);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn gpu2_push_clip_rect(ptr: *mut CanvasHandle, position: Vector2, size: Vector2)
+{
+	handle(ptr).push_clip_rect(position, size);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn gpu2_push_clip_polygon(ptr: *mut CanvasHandle, top_left: Vector2, top_right: Vector2, bottom_left: Vector2, bottom_right: Vector2)
+{
+	handle(ptr).push_clip_polygon(top_left, top_right, bottom_left, bottom_right);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn gpu2_pop_geometry(ptr: *mut CanvasHandle)
+{
+	handle(ptr).pop_geometry();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn gpu2_pop_clip(ptr: *mut CanvasHandle)
+{
+	handle(ptr).pop_clip();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn gpu2_flush(ptr: *mut CanvasHandle)
+{
+	handle(ptr).flush();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn gpu2_measure_text(ptr: *mut CanvasHandle, cstr: *const c_char, size: f32, monospace: bool) -> Vector2
+{
+	handle(ptr).measure_text(c2str(cstr), size, monospace)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn create_network(port_start: u16, buffer_size: usize, handler: *mut EventHandler) -> UIDHandle<NetworkComponent>
+{
+	let mut network = NetworkComponent::new(port_start, buffer_size);
+	if handler.is_null()
+	{
+		network.listen(None);
+	}
+	else
+	{
+		network.listen(Some((*handler).new_emitter(network.uid())));
+	}
 	UIDHandle::new(network)
 }
 
@@ -387,14 +792,15 @@ pub unsafe extern "C" fn create_filesystem(root_path: *const c_char, name: *cons
 	Box::into_raw(Box::new(fs))
 }
 
+// Nicht mount/unmount, siehe write_char.
 #[no_mangle]
-pub unsafe extern "C" fn mount(ptr: *mut Filesystem, c_device: *const c_char, c_mount: *const c_char) -> bool
+pub unsafe extern "C" fn fs_mount(ptr: *mut Filesystem, c_device: *const c_char, c_mount: *const c_char) -> bool
 {
 	handle(ptr).mount(c2str(c_device), c2str(c_mount))
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn unmount(ptr: *mut Filesystem, c_device: *const c_char) -> bool
+pub unsafe extern "C" fn fs_unmount(ptr: *mut Filesystem, c_device: *const c_char) -> bool
 {
 	handle(ptr).unmount(c2str(c_device))
 }
@@ -2,12 +2,13 @@
 use core::slice;
 use std::{net::{Ipv4Addr, SocketAddrV4, UdpSocket}, thread, sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}}, collections::HashMap, alloc::Layout};
 
-use nanoserde::SerBin;
 use rand::Rng;
 
-use crate::{EventEmitter, component::{UID, Component, generateUID, UID_SIZE}, Signal, events::C_Param};
+use crate::{EventEmitter, component::{UID, Component, generateUID, UID_SIZE}, Signal, events::{Value, PARAM_NIL, PARAM_BOOL, PARAM_INT, PARAM_FLOAT, PARAM_STRING, PARAM_ARRAY, PARAM_STRUCT, PARAM_COMPONENT}};
 
 const EVENT_NETWORK_MESSAGE: &str = "NetworkMessage\0";
+/// Maximale Verschachtelung von Arrays und Structs in einer Nachricht.
+const MAX_VALUE_DEPTH: usize = 32;
 
 struct SocketListener
 {
@@ -55,44 +56,35 @@ impl NetworkComponent
 				self.sockets.insert(port, listener.clone());
 				let emitter = self.emitter.clone();
 				thread::spawn(move || {
-					let mut buf = alloc_buffer(buffer_size);
-					while listener.active.load(Ordering::Relaxed) == true
+					let buf = alloc_buffer(buffer_size);
+					while listener.active.load(Ordering::Relaxed)
 					{
-						match listener.socket.recv_from(&mut buf)
+						match listener.socket.recv_from(buf)
 						{
-							Ok((_size, _addr)) => {
-								let sender_uid = uid_from_buffer(&buf, 0);
+							Ok((size, _addr)) => {
+								if size < UID_SIZE { eprintln!("Network Error message too short"); continue; }
+								let sender_uid = uid_from_buffer(buf, 0);
 
-								let mut offset: usize = UID_SIZE;
-								let data: Vec<String> = nanoserde::DeBin::de_bin(&mut offset, &buf).unwrap();
-
-								let mut params = Vec::<C_Param>::new();
-								params.push(C_Param::from_str(&sender_uid));
-								params.push(C_Param::from(&port, true));
+								let data = match decode_values(&buf[UID_SIZE..size])
+								{
+									Ok(data) => data,
+									Err(e) => { eprintln!("Network Error {}", e); continue; }
+								};
 
-								data.iter().for_each(|d| params.push(C_Param::from_str(&d)));
+								let mut params = vec![Value::String(sender_uid), Value::from(port)];
+								params.extend(data);
 
 								let mut lock = emitter.lock().unwrap();
 								let e = lock.as_mut().unwrap();
 								
-								let signal = Signal::raw(EVENT_NETWORK_MESSAGE, e.owner(), params);
-
-								/*println!("len: {}", signal.len);
-								for i in 0..(signal.len)
-								{
-									let param = signal.args[i];
-									println!("offset: {} len {}", i, param.len);
-									//std::slice::from_raw_parts(param.ptr, param.len).;
-
-									println!("Daten: {}: {}", i, param.ptr as usize);
-								}*/
+								let signal = Signal::new(EVENT_NETWORK_MESSAGE, e.owner(), params);
 
 								e.send(signal);
 							},
 							Err(e) => { eprintln!("{}", e); }
 						}
 					}
-					drop_buffer(&mut buf, buffer_size);
+					drop_buffer(buf, buffer_size);
 				});
 				true
 			},
@@ -121,7 +113,7 @@ impl NetworkComponent
 		self.sockets.clear();
 	}
 
-	pub fn send(&mut self, _reciever: &str, port: u16, data: Vec::<String>)
+	pub fn send(&mut self, _reciever: &str, port: u16, data: Vec::<Value>)
 	{
 		if self.sender_socket.is_none()
 		{
@@ -135,7 +127,12 @@ impl NetworkComponent
 
 			let mut buffer = Vec::<u8>::default();
 			buffer.append(&mut self.id.to_vec());
-			data.ser_bin(&mut buffer);
+			encode_values(&data, &mut buffer);
+			if buffer.len() > self.buffer_size
+			{
+				eprintln!("Network Error message of {} bytes exceeds the network buffer", buffer.len());
+				return;
+			}
 
 			let result = socket.send_to(&buffer, addr);
 			match result
@@ -154,7 +151,7 @@ impl NetworkComponent
 		self.sender_socket = UdpSocket::bind(addr).ok();
 	}
 
-	pub fn broadcast(&mut self, port: u16, data: Vec::<String>)
+	pub fn broadcast(&mut self, port: u16, data: Vec::<Value>)
 	{
 		self.send("", port, data);
 	}
@@ -192,5 +189,202 @@ fn drop_buffer(buffer: &mut [u8], size: usize)
 #[inline]
 fn uid_from_buffer(buf: &[u8], off: usize) -> String
 {
-	unsafe { String::from_utf8_unchecked(buf[off..off+UID_SIZE].to_owned()) }
+	String::from_utf8_lossy(&buf[off..off+UID_SIZE]).into_owned()
+}
+
+/// Schreibt Werte im Nachrichtenformat.
+/// Jeder Wert beginnt mit seinem PARAM Typ, Längen und Zahlen sind Little Endian.
+fn encode_values(values: &[Value], out: &mut Vec<u8>)
+{
+	out.extend((values.len() as u32).to_le_bytes());
+	for value in values
+	{
+		encode_value(value, out);
+	}
+}
+
+fn encode_value(value: &Value, out: &mut Vec<u8>)
+{
+	match value
+	{
+		Value::Nil => out.push(PARAM_NIL),
+		Value::Bool(v) => out.extend([PARAM_BOOL, *v as u8]),
+		Value::Int(v) => { out.push(PARAM_INT); out.extend(v.to_le_bytes()); },
+		Value::Float(v) => { out.push(PARAM_FLOAT); out.extend(v.to_le_bytes()); },
+		Value::String(v) => { out.push(PARAM_STRING); encode_str(v, out); },
+		Value::Component(v) => { out.push(PARAM_COMPONENT); encode_str(v, out); },
+		Value::Array(items) => { out.push(PARAM_ARRAY); encode_values(items, out); },
+		Value::Struct(fields) => {
+			out.push(PARAM_STRUCT);
+			out.extend((fields.len() as u32).to_le_bytes());
+			for (key, value) in fields
+			{
+				encode_str(key, out);
+				encode_value(value, out);
+			}
+		},
+	}
+}
+
+#[inline]
+fn encode_str(s: &str, out: &mut Vec<u8>)
+{
+	out.extend((s.len() as u32).to_le_bytes());
+	out.extend(s.as_bytes());
+}
+
+/// Liest mit [`encode_values`] geschriebene Werte.
+/// Nachrichten kommen von beliebigen Absendern, daher wird jede Länge gegen die
+/// verbleibenden Bytes geprüft, bevor Speicher reserviert wird, und die Tiefe ist begrenzt.
+fn decode_values(buf: &[u8]) -> Result<Vec<Value>, &'static str>
+{
+	let mut reader = Reader{ buf, depth: 0 };
+	let values = reader.values()?;
+	if !reader.buf.is_empty() { return Err("trailing bytes in message"); }
+	Ok(values)
+}
+
+struct Reader<'a>
+{
+	buf: &'a [u8],
+	depth: usize,
+}
+
+impl<'a> Reader<'a>
+{
+	fn bytes(&mut self, len: usize) -> Result<&'a [u8], &'static str>
+	{
+		if len > self.buf.len() { return Err("truncated message"); }
+		let (bytes, rest) = self.buf.split_at(len);
+		self.buf = rest;
+		Ok(bytes)
+	}
+
+	fn array<const N: usize>(&mut self) -> Result<[u8; N], &'static str>
+	{
+		Ok(self.bytes(N)?.try_into().unwrap())
+	}
+
+	/// Anzahl folgender Einträge. Jeder Eintrag belegt mindestens `min_size` Bytes.
+	fn count(&mut self, min_size: usize) -> Result<usize, &'static str>
+	{
+		let count = u32::from_le_bytes(self.array()?) as usize;
+		if count.saturating_mul(min_size) > self.buf.len() { return Err("invalid length in message"); }
+		Ok(count)
+	}
+
+	fn string(&mut self) -> Result<String, &'static str>
+	{
+		let len = self.count(1)?;
+		let bytes = self.bytes(len)?;
+		String::from_utf8(bytes.to_vec()).map_err(|_| "invalid string in message")
+	}
+
+	fn values(&mut self) -> Result<Vec<Value>, &'static str>
+	{
+		let count = self.count(1)?;
+		let mut values = Vec::with_capacity(count);
+		for _ in 0..count
+		{
+			values.push(self.value()?);
+		}
+		Ok(values)
+	}
+
+	fn value(&mut self) -> Result<Value, &'static str>
+	{
+		let value = match self.array::<1>()?[0]
+		{
+			PARAM_NIL => Value::Nil,
+			PARAM_BOOL => Value::Bool(self.array::<1>()?[0] != 0),
+			PARAM_INT => Value::Int(i64::from_le_bytes(self.array()?)),
+			PARAM_FLOAT => Value::Float(f64::from_le_bytes(self.array()?)),
+			PARAM_STRING => Value::String(self.string()?),
+			PARAM_COMPONENT => Value::Component(self.string()?),
+			kind @ (PARAM_ARRAY | PARAM_STRUCT) => {
+				if self.depth >= MAX_VALUE_DEPTH { return Err("message nested too deeply"); }
+				self.depth += 1;
+				let value = match kind
+				{
+					PARAM_ARRAY => Value::Array(self.values()?),
+					_ => {
+						// Schlüssellänge und Typ des Werts
+						let count = self.count(5)?;
+						let mut fields = Vec::with_capacity(count);
+						for _ in 0..count
+						{
+							fields.push((self.string()?, self.value()?));
+						}
+						Value::Struct(fields)
+					}
+				};
+				self.depth -= 1;
+				value
+			},
+			_ => return Err("unknown value type in message"),
+		};
+		Ok(value)
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	fn encoded(values: &[Value]) -> Vec<u8>
+	{
+		let mut buf = Vec::new();
+		encode_values(values, &mut buf);
+		buf
+	}
+
+	#[test]
+	fn values_survive_the_network_format()
+	{
+		let values = vec![
+			Value::Nil,
+			Value::Bool(true),
+			Value::Int(-42),
+			Value::Float(1.5),
+			Value::from("ping"),
+			Value::Component("0123".to_string()),
+			Value::Array(vec![Value::Int(1), Value::Array(Vec::new())]),
+			Value::Struct(vec![("r".to_string(), Value::Float(1.0)), ("name".to_string(), Value::from("x"))]),
+		];
+		assert_eq!(decode_values(&encoded(&values)), Ok(values));
+	}
+
+	#[test]
+	fn malformed_messages_are_rejected()
+	{
+		// Eine riesige Länge darf keinen Speicher reservieren.
+		assert_eq!(decode_values(&u32::MAX.to_le_bytes()), Err("invalid length in message"));
+		let mut huge_string = encoded(&[Value::from("x")]);
+		huge_string[5..9].copy_from_slice(&u32::MAX.to_le_bytes());
+		assert_eq!(decode_values(&huge_string), Err("invalid length in message"));
+
+		let message = encoded(&[Value::Int(7)]);
+		assert_eq!(decode_values(&message[..message.len()-1]), Err("truncated message"));
+		assert_eq!(decode_values(&[message.as_slice(), &[0]].concat()), Err("trailing bytes in message"));
+		assert_eq!(decode_values(&[1, 0, 0, 0, 99]), Err("unknown value type in message"));
+	}
+
+	#[test]
+	fn deeply_nested_messages_are_rejected()
+	{
+		let nested = |depth: usize| (0..depth).fold(Value::Nil, |v, _| Value::Array(vec![v]));
+		assert!(decode_values(&encoded(&[nested(MAX_VALUE_DEPTH)])).is_ok());
+		assert_eq!(decode_values(&encoded(&[nested(MAX_VALUE_DEPTH + 1)])), Err("message nested too deeply"));
+
+		// Ohne Begrenzung würde diese Nachricht den Stack überlaufen lassen.
+		let mut message = 1u32.to_le_bytes().to_vec();
+		for _ in 0..100_000
+		{
+			message.push(PARAM_ARRAY);
+			message.extend(1u32.to_le_bytes());
+		}
+		message.push(PARAM_NIL);
+		assert_eq!(decode_values(&message), Err("message nested too deeply"));
+	}
 }
\ No newline at end of file
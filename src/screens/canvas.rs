@@ -0,0 +1,389 @@
+use std::sync::{Arc, Mutex};
+
+use unicode_segmentation::UnicodeSegmentation;
+use wgpu_glyph::ab_glyph::{Font, PxScale, ScaleFont};
+
+use super::{Color, ScreenContent, cluster_width};
+use super::screen::ScreenComponent;
+use super::text::default_font;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Vector2
+{
+	pub x: f32,
+	pub y: f32,
+}
+
+impl Vector2
+{
+	pub const ZERO: Self = Self{x: 0.0, y: 0.0};
+
+	#[inline]
+	pub fn new(x: f32, y: f32) -> Self
+	{
+		Self{x, y}
+	}
+
+	#[inline]
+	pub fn add(self, o: Vector2) -> Self
+	{
+		Self{x: self.x + o.x, y: self.y + o.y}
+	}
+
+	#[inline]
+	pub fn sub(self, o: Vector2) -> Self
+	{
+		Self{x: self.x - o.x, y: self.y - o.y}
+	}
+
+	#[inline]
+	pub fn mul(self, v: f32) -> Self
+	{
+		Self{x: self.x * v, y: self.y * v}
+	}
+
+	#[inline]
+	pub fn length(self) -> f32
+	{
+		(self.x * self.x + self.y * self.y).sqrt()
+	}
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Vector4
+{
+	pub x: f32,
+	pub y: f32,
+	pub z: f32,
+	pub w: f32,
+}
+
+/// Affine 2D Transformation.
+/// x' = a*x + c*y + e, y' = b*x + d*y + f
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform2D
+{
+	pub m: [f32; 6],
+}
+
+impl Transform2D
+{
+	pub const IDENTITY: Self = Self{m: [1.0, 0.0, 0.0, 1.0, 0.0, 0.0]};
+
+	pub fn translate(v: Vector2) -> Self
+	{
+		Self{m: [1.0, 0.0, 0.0, 1.0, v.x, v.y]}
+	}
+
+	/// Rotation in Grad, wie bei FIN.
+	pub fn rotate(degrees: f32) -> Self
+	{
+		let (sin, cos) = degrees.to_radians().sin_cos();
+		Self{m: [cos, sin, -sin, cos, 0.0, 0.0]}
+	}
+
+	pub fn scale(v: Vector2) -> Self
+	{
+		Self{m: [v.x, 0.0, 0.0, v.y, 0.0, 0.0]}
+	}
+
+	/// Verknüpft beide Transformationen. `local` wird zuerst angewendet.
+	pub fn then(&self, local: &Transform2D) -> Self
+	{
+		let [a1, b1, c1, d1, e1, f1] = self.m;
+		let [a2, b2, c2, d2, e2, f2] = local.m;
+		Self{m: [
+			a1 * a2 + c1 * b2,
+			b1 * a2 + d1 * b2,
+			a1 * c2 + c1 * d2,
+			b1 * c2 + d1 * d2,
+			a1 * e2 + c1 * f2 + e1,
+			b1 * e2 + d1 * f2 + f1,
+		]}
+	}
+
+	#[inline]
+	pub fn apply(&self, p: Vector2) -> Vector2
+	{
+		let [a, b, c, d, e, f] = self.m;
+		Vector2{x: a * p.x + c * p.y + e, y: b * p.x + d * p.y + f}
+	}
+
+	/// Mittlere Skalierung, z.B. für Linienstärken.
+	#[inline]
+	pub fn scale_factor(&self) -> f32
+	{
+		let [a, b, c, d, _, _] = self.m;
+		(a * d - b * c).abs().sqrt()
+	}
+
+	/// Spaltenweise 4x4 Matrix für Shader.
+	pub fn matrix(&self) -> [f32; 16]
+	{
+		let [a, b, c, d, e, f] = self.m;
+		[
+			a,   b,   0.0, 0.0,
+			c,   d,   0.0, 0.0,
+			0.0, 0.0, 1.0, 0.0,
+			e,   f,   0.0, 1.0,
+		]
+	}
+}
+
+/// Achsenparalleler Clip Bereich in Pixeln.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClipRect
+{
+	pub x: f32,
+	pub y: f32,
+	pub width: f32,
+	pub height: f32,
+}
+
+impl ClipRect
+{
+	/// Umschließendes Rechteck der transformierten Punkte.
+	pub fn bounding(points: &[Vector2]) -> Self
+	{
+		let mut min = Vector2::new(f32::MAX, f32::MAX);
+		let mut max = Vector2::new(f32::MIN, f32::MIN);
+		for p in points
+		{
+			min = Vector2::new(min.x.min(p.x), min.y.min(p.y));
+			max = Vector2::new(max.x.max(p.x), max.y.max(p.y));
+		}
+		Self{x: min.x, y: min.y, width: max.x - min.x, height: max.y - min.y}
+	}
+
+	pub fn intersect(&self, other: &ClipRect) -> Self
+	{
+		let x = self.x.max(other.x);
+		let y = self.y.max(other.y);
+		let r = (self.x + self.width).min(other.x + other.width);
+		let b = (self.y + self.height).min(other.y + other.height);
+		Self{x, y, width: (r - x).max(0.0), height: (b - y).max(0.0)}
+	}
+}
+
+/// Einstellungen von drawBox. Entspricht dem FIN Struct GPUT2DrawCallBox.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct BoxSettings
+{
+	pub position: Vector2,
+	pub size: Vector2,
+	pub rotation: f32,
+	pub color: Color,
+	pub has_centered_origin: bool,
+	pub is_rounded: bool,
+	/// Eckradien: oben links, oben rechts, unten rechts, unten links.
+	pub radii: Vector4,
+	pub has_outline: bool,
+	pub outline_thickness: f32,
+	pub outline_color: Color,
+}
+
+#[derive(Clone, Debug)]
+pub enum DrawCall
+{
+	Rect { position: Vector2, size: Vector2, color: Color, rotation: f32 },
+	Line { points: Vec<Vector2>, thickness: f32, color: Color },
+	Box(BoxSettings),
+	Text { position: Vector2, text: String, size: f32, color: Color, monospace: bool },
+	Spline { start: Vector2, start_direction: Vector2, end: Vector2, end_direction: Vector2, thickness: f32, color: Color },
+	Bezier { p0: Vector2, p1: Vector2, p2: Vector2, p3: Vector2, thickness: f32, color: Color },
+}
+
+/// Ein Zeichenbefehl mit der zum Zeitpunkt des Aufrufs gültigen Geometrie und Clip Region.
+#[derive(Clone, Debug)]
+pub struct DrawCommand
+{
+	pub call: DrawCall,
+	pub transform: Transform2D,
+	pub clip: Option<ClipRect>,
+}
+
+/// Das zuletzt mit flush übergebene Bild einer GPU T2.
+#[derive(Clone, Debug, Default)]
+pub struct Frame
+{
+	pub width: u32,
+	pub height: u32,
+	pub commands: Vec<DrawCommand>,
+}
+
+/// GPU T2 mit Pixel basierter Zeichen API.
+/// Befehle werden gesammelt und erst bei flush an den Bildschirm übergeben.
+pub struct CanvasHandle
+{
+	pub frame: Arc<Mutex<Frame>>,
+	pub screen: Option<ScreenComponent>,
+	commands: Vec<DrawCommand>,
+	transforms: Vec<Transform2D>,
+	clips: Vec<Option<ClipRect>>,
+}
+
+impl CanvasHandle
+{
+	pub fn new(width: u32, height: u32) -> Self
+	{
+		Self
+		{
+			frame: Arc::new(Mutex::new(Frame{width, height, commands: Vec::new()})),
+			screen: None,
+			commands: Vec::new(),
+			transforms: Vec::new(),
+			clips: Vec::new(),
+		}
+	}
+
+	pub fn bind_screen(&mut self, screen: Option<ScreenComponent>)
+	{
+		self.screen = screen;
+		if let Some(screen) = self.screen.as_mut()
+		{
+			screen.open(ScreenContent::Canvas(self.frame.clone()));
+		}
+	}
+
+	pub fn size(&self) -> Vector2
+	{
+		let frame = self.frame.lock().unwrap();
+		Vector2::new(frame.width as f32, frame.height as f32)
+	}
+
+	pub fn draw(&mut self, call: DrawCall)
+	{
+		let transform = self.transform();
+		let clip = self.clip();
+		self.commands.push(DrawCommand{call, transform, clip});
+	}
+
+	pub fn push_transform(&mut self, translation: Vector2, rotation: f32, scale: Vector2)
+	{
+		let local = Transform2D::translate(translation)
+			.then(&Transform2D::rotate(rotation))
+			.then(&Transform2D::scale(scale));
+		self.transforms.push(self.transform().then(&local));
+	}
+
+	/// Layouts verschieben und skalieren alle folgenden Zeichenbefehle.
+	/// Die Größe hat für Freen keine Bedeutung, da es keine Layout Ausrichtung gibt.
+	pub fn push_layout(&mut self, offset: Vector2, _size: Vector2, scale: f32)
+	{
+		let local = Transform2D::translate(offset).then(&Transform2D::scale(Vector2::new(scale, scale)));
+		self.transforms.push(self.transform().then(&local));
+	}
+
+	pub fn pop_geometry(&mut self)
+	{
+		self.transforms.pop();
+	}
+
+	pub fn push_clip_rect(&mut self, position: Vector2, size: Vector2)
+	{
+		self.push_clip_polygon(position, position.add(Vector2::new(size.x, 0.0)),
+			position.add(Vector2::new(0.0, size.y)), position.add(size));
+	}
+
+	/// Polygone werden auf ihr umschließendes Rechteck reduziert.
+	pub fn push_clip_polygon(&mut self, top_left: Vector2, top_right: Vector2, bottom_left: Vector2, bottom_right: Vector2)
+	{
+		let t = self.transform();
+		let mut rect = ClipRect::bounding(&[t.apply(top_left), t.apply(top_right), t.apply(bottom_left), t.apply(bottom_right)]);
+		if let Some(parent) = self.clip()
+		{
+			rect = rect.intersect(&parent);
+		}
+		self.clips.push(Some(rect));
+	}
+
+	pub fn pop_clip(&mut self)
+	{
+		self.clips.pop();
+	}
+
+	pub fn flush(&mut self)
+	{
+		{
+			let mut frame = self.frame.lock().unwrap();
+			frame.commands = std::mem::take(&mut self.commands);
+		}
+		self.transforms.clear();
+		self.clips.clear();
+		if let Some(screen) = self.screen.as_ref()
+		{
+			screen.flush();
+		}
+	}
+
+	/// Misst die Ausdehnung eines Textes in Pixeln.
+	/// Mit monospace belegt jedes Graphem Cluster wie in [`monospace_columns`] feste Spalten.
+	pub fn measure_text(&self, text: &str, size: f32, monospace: bool) -> Vector2
+	{
+		let font = default_font();
+		let scaled = font.as_scaled(PxScale::from(size));
+		let width = if monospace
+		{
+			let columns: i32 = text.graphemes(true).map(cluster_width).sum();
+			columns as f32 * monospace_advance(size)
+		}
+		else
+		{
+			text.chars().map(|c| scaled.h_advance(font.glyph_id(c))).sum()
+		};
+		Vector2::new(width, scaled.height())
+	}
+
+	#[inline]
+	fn transform(&self) -> Transform2D
+	{
+		*self.transforms.last().unwrap_or(&Transform2D::IDENTITY)
+	}
+
+	#[inline]
+	fn clip(&self) -> Option<ClipRect>
+	{
+		self.clips.last().copied().flatten()
+	}
+}
+
+/// Vorschub einer Spalte in Monospace Texten, die Breite von 'M'.
+pub fn monospace_advance(size: f32) -> f32
+{
+	let font = default_font();
+	font.as_scaled(PxScale::from(size)).h_advance(font.glyph_id('M'))
+}
+
+/// Graphem Cluster eines Monospace Textes mit ihrer ersten Spalte.
+/// Breite Zeichen belegen wie in Buffer::writeText zwei Spalten, kombinierende Zeichen keine eigene.
+pub fn monospace_columns(text: &str) -> Vec<(i32, &str)>
+{
+	let mut column = 0;
+	text.graphemes(true).map(|cluster| {
+		let start = column;
+		column += cluster_width(cluster);
+		(start, cluster)
+	}).collect()
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	#[test]
+	fn monospace_text_uses_cell_advance()
+	{
+		let canvas = CanvasHandle::new(100, 100);
+		let cell = canvas.measure_text("M", 20.0, true);
+		assert!(cell.x > 0.0);
+		assert_eq!(canvas.measure_text("iW\u{0301}x", 20.0, true).x, cell.x * 3.0);
+		assert_eq!(canvas.measure_text("漢a", 20.0, true).x, cell.x * 3.0);
+		assert_eq!(monospace_columns("a漢e\u{0301}"), [(0, "a"), (1, "漢"), (3, "e\u{0301}")]);
+		assert_eq!(canvas.measure_text("", 20.0, false).x, 0.0);
+		assert_eq!(canvas.measure_text("Hallo", 20.0, false).y, cell.y);
+	}
+}
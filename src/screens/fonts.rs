@@ -0,0 +1,107 @@
+use std::fs;
+use std::sync::Mutex;
+
+use wgpu_glyph::ab_glyph::{Font, FontArc, PxScale, ScaleFont};
+
+use super::Size;
+use super::text::default_font;
+
+/// Vom FREEN Konfigurations Objekt gesetzte Schriftarten.
+static CONFIG: Mutex<FontConfig> = Mutex::new(FontConfig{ paths: Vec::new(), fonts: Vec::new(), generation: 0 });
+
+struct FontConfig
+{
+	paths: Vec<String>,
+	fonts: Vec<FontArc>,
+	generation: u64,
+}
+
+/// Geordnete Liste von Schriftarten für das Zeichenraster.
+/// Jedes Zeichen wird mit der ersten Schriftart gezeichnet, die es enthält.
+/// Die eingebettete Fira Mono ist immer der letzte Eintrag.
+#[derive(Clone)]
+pub struct FontSet
+{
+	pub fonts: Vec<FontArc>,
+	/// Ändert sich mit jeder neuen Konfiguration, damit zwischengespeicherte Renderer neu erzeugt werden.
+	pub generation: u64,
+}
+
+impl FontSet
+{
+	/// Die aktuell konfigurierten Schriftarten.
+	pub fn current() -> Self
+	{
+		let config = CONFIG.lock().unwrap();
+		let mut fonts = config.fonts.clone();
+		fonts.push(default_font());
+		Self{ fonts, generation: config.generation }
+	}
+
+	/// Die Generation der aktuellen Konfiguration, ohne die Schriftarten zu kopieren.
+	pub fn generation() -> u64
+	{
+		CONFIG.lock().unwrap().generation
+	}
+
+	/// Index der ersten Schriftart mit einer Glyphe für das Zeichen.
+	/// Fehlt das Zeichen in allen Schriftarten, wird die erste verwendet.
+	pub fn font_for(&self, ch: char) -> usize
+	{
+		self.fonts.iter().position(|f| f.glyph_id(ch).0 != 0).unwrap_or(0)
+	}
+
+	/// Zellgröße aus den Metriken der ersten Schriftart.
+	/// Die Breite ist der Vorschub von 'M', die Höhe entspricht der Schriftgröße.
+	pub fn cell_size(&self, font_size: u32) -> Size
+	{
+		let font = &self.fonts[0];
+		let scaled = font.as_scaled(PxScale::from(font_size as f32));
+		let width = scaled.h_advance(font.glyph_id('M')).round() as u32;
+		let height = scaled.height().ceil() as u32;
+		Size{ width: width.max(1), height: height.max(1) }
+	}
+}
+
+/// Setzt die Schriftart und ihre Ausweichschriftarten in absteigender Priorität.
+/// Nicht lesbare Dateien werden übersprungen. Unveränderte Pfade werden nicht neu geladen.
+pub fn set_font_paths(paths: &[&str])
+{
+	if CONFIG.lock().unwrap().paths == paths { return; }
+
+	let mut fonts = Vec::with_capacity(paths.len());
+	for path in paths
+	{
+		match fs::read(path).map_err(|e| e.to_string()).and_then(|data| FontArc::try_from_vec(data).map_err(|e| e.to_string()))
+		{
+			Ok(font) => fonts.push(font),
+			Err(e) => eprintln!("Font Error {}: {}", path, e)
+		}
+	}
+	let mut config = CONFIG.lock().unwrap();
+	config.paths = paths.iter().map(|p| p.to_string()).collect();
+	config.fonts = fonts;
+	config.generation += 1;
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	#[test]
+	fn unchanged_fonts_keep_their_generation()
+	{
+		let start = FontSet::generation();
+		set_font_paths(&["does-not-exist.ttf"]);
+		let changed = FontSet::generation();
+		assert!(changed > start);
+		set_font_paths(&["does-not-exist.ttf"]);
+		assert_eq!(FontSet::generation(), changed);
+
+		// Nicht lesbare Dateien fallen weg, die Standard Schriftart bleibt.
+		assert_eq!(FontSet::current().fonts.len(), 1);
+		set_font_paths(&[]);
+		assert!(FontSet::generation() > changed);
+	}
+}
@@ -4,7 +4,7 @@ use wgpu::{Device, RenderPipeline, Extent3d};
 use wgpu::util::DeviceExt;
 use ultraviolet::Mat4;
 
-use super::{Buffer, ScreenSize};
+use super::{Buffer, Rect, ScreenSize};
 
 
 pub struct PixelGrid
@@ -16,14 +16,15 @@ impl PixelGrid
 {
 	pub fn new(device: & Device, size: ScreenSize, texture_format: wgpu::TextureFormat) -> Self
 	{
-		let renderer = GridRenderer::new(&device, texture_format, size);
+		let renderer = GridRenderer::new(device, texture_format, size);
 
 		Self {renderer}
 	}
 
-	pub fn draw_queued(&self, device: &Device, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView, buffer: &Buffer)
+	/// Zeichnet den Hintergrund. Nur die geänderten Bereiche werden hochgeladen.
+	pub fn draw_queued(&mut self, device: &Device, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView, buffer: &Buffer, dirty: &[Rect])
 	{
-		self.renderer.draw(device, queue, encoder, buffer, target);
+		self.renderer.draw(device, queue, encoder, buffer, dirty, target);
 	}
 
 	pub fn resize(&mut self, device: &Device, size: ScreenSize, queue: &wgpu::Queue)
@@ -57,11 +58,15 @@ struct GridRenderer
 	size: ScreenSize,
 	pipeline: RenderPipeline,
 	uniform_buffer: wgpu::Buffer,
+	bind_group_layout: wgpu::BindGroupLayout,
 	bind_group: wgpu::BindGroup,
+	sampler: wgpu::Sampler,
 	vertex_buffer: wgpu::Buffer,
 	clip_rect: (u32, u32, u32, u32),
 	texture: wgpu::Texture,
-	texture_size: Extent3d
+	texture_size: Extent3d,
+	/// Die Textur muss vollständig hochgeladen werden, z.B. nach Größenänderungen.
+	invalid: bool,
 }
 
 impl GridRenderer
@@ -69,7 +74,7 @@ impl GridRenderer
 	fn new(device: &Device, texture_format: wgpu::TextureFormat, size: ScreenSize) -> GridRenderer
 	{
 		let shader = device.create_shader_module(&wgpu::include_wgsl!("../shaders/shader.wgsl"));
-		let (texture, texture_size) = PixelGrid::create_texture(&device, &size);
+		let (texture, texture_size) = PixelGrid::create_texture(device, &size);
 		
 		let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
 			label: Some("grid:renderer_sampler"),
@@ -101,10 +106,7 @@ impl GridRenderer
 			attributes: &wgpu::vertex_attr_array![0 => Float32x2],
 		};
 		
-		let matrix = ScalingMatrix::new(
-			(texture_size.width as f32, texture_size.height as f32),
-			(size.window_width as f32, size.window_height as f32),
-		);
+		let matrix = ScalingMatrix::new(&size);
 		let transform_bytes = matrix.as_bytes();
 		let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
 			label: Some("grid:renderer_matrix_uniform_buffer"),
@@ -112,8 +114,6 @@ impl GridRenderer
 			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
 		});
 
-		let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-
 		let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
 			label: Some("grid:renderer_bind_group_layout"),
 			entries: &[
@@ -145,24 +145,7 @@ impl GridRenderer
 				},
 			],
 		});
-		let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-			label: Some("grid:renderer_bind_group"),
-			layout: &bind_group_layout,
-			entries: &[
-				wgpu::BindGroupEntry {
-					binding: 0,
-					resource: wgpu::BindingResource::TextureView(&texture_view),
-				},
-				wgpu::BindGroupEntry {
-					binding: 1,
-					resource: wgpu::BindingResource::Sampler(&sampler),
-				},
-				wgpu::BindGroupEntry {
-					binding: 2,
-					resource: uniform_buffer.as_entire_binding(),
-				},
-			],
-		});
+		let bind_group = GridRenderer::create_bind_group(device, &bind_group_layout, &texture, &sampler, &uniform_buffer);
 
 		let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
 			label: Some("grid:renderer_pipeline_layout"),
@@ -202,38 +185,38 @@ impl GridRenderer
 			size,
 			pipeline,
 			uniform_buffer,
+			bind_group_layout,
 			bind_group,
+			sampler,
 			vertex_buffer,
 			clip_rect,
 			texture,
-			texture_size
+			texture_size,
+			invalid: true,
 		}
 	}
 
-	fn draw(&self,
+	fn draw(&mut self,
 		_device: &wgpu::Device,
 		queue: &wgpu::Queue,
 		encoder: &mut wgpu::CommandEncoder,
 		buffer: &Buffer,
+		dirty: &[Rect],
 		target: &wgpu::TextureView)
 	{
-		let bytes_per_row = (self.texture_size.width as f32 * 4.0) as u32;
-		let buffer_data: Vec<u8> = buffer.background.iter().flat_map(|col| col.bytes().to_vec().into_iter()).collect();
-		queue.write_texture(
-			wgpu::ImageCopyTexture {
-				texture: &self.texture,
-				mip_level: 0,
-				origin: wgpu::Origin3d { x: 0, y: 0, z: 0 },
-				aspect: wgpu::TextureAspect::All,
-			},
-			&buffer_data,
-			wgpu::ImageDataLayout {
-				offset: 0,
-				bytes_per_row: NonZeroU32::new(bytes_per_row),
-				rows_per_image: NonZeroU32::new(self.texture_size.height),
-			},
-			self.texture_size,
-		);
+		if buffer.width != self.texture_size.width || buffer.height != self.texture_size.height { return; }
+		if self.invalid
+		{
+			self.upload(queue, buffer, buffer.bounds());
+			self.invalid = false;
+		}
+		else
+		{
+			for rect in dirty
+			{
+				self.upload(queue, buffer, *rect);
+			}
+		}
 
 		let mut rpass =
         encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
@@ -261,22 +244,75 @@ impl GridRenderer
         rpass.draw(0..3, 0..1);
 	}
 
+	/// Lädt die Hintergrundfarben eines Bereichs in die Textur.
+	fn upload(&self, queue: &wgpu::Queue, buffer: &Buffer, rect: Rect)
+	{
+		let rect = rect.intersect(&buffer.bounds());
+		if rect.area() == 0 { return; }
+		let mut buffer_data: Vec<u8> = Vec::with_capacity(rect.area() as usize * 4);
+		for y in rect.y..(rect.y + rect.height)
+		{
+			let start = (y * buffer.width + rect.x) as usize;
+			buffer_data.extend(buffer.background[start..(start + rect.width as usize)].iter().flat_map(|col| col.bytes()));
+		}
+		queue.write_texture(
+			wgpu::ImageCopyTexture {
+				texture: &self.texture,
+				mip_level: 0,
+				origin: wgpu::Origin3d { x: rect.x, y: rect.y, z: 0 },
+				aspect: wgpu::TextureAspect::All,
+			},
+			&buffer_data,
+			wgpu::ImageDataLayout {
+				offset: 0,
+				bytes_per_row: NonZeroU32::new(rect.width * 4),
+				rows_per_image: NonZeroU32::new(rect.height),
+			},
+			wgpu::Extent3d { width: rect.width, height: rect.height, depth_or_array_layers: 1 },
+		);
+	}
+
 	pub fn resize(&mut self, size: ScreenSize, device: &wgpu::Device, queue: &wgpu::Queue)
 	{
+		if size.grid_width != self.size.grid_width || size.grid_height != self.size.grid_height
+		{
+			let (texture, extent) = PixelGrid::create_texture(device, &size);
+			self.bind_group = GridRenderer::create_bind_group(device, &self.bind_group_layout, &texture, &self.sampler, &self.uniform_buffer);
+			self.texture = texture;
+			self.texture_size = extent;
+			self.invalid = true;
+		}
 		self.size = size;
-		let (texture, extent) = PixelGrid::create_texture(device, &self.size);
-		self.texture = texture;
-		self.texture_size = extent;
 
-		let matrix = ScalingMatrix::new(
-			(self.size.grid_width as f32, self.size.grid_height as f32),
-			(self.size.window_width as f32, self.size.window_height as f32),
-		);
+		let matrix = ScalingMatrix::new(&self.size);
 		let transform_bytes = matrix.as_bytes();
         queue.write_buffer(&self.uniform_buffer, 0, transform_bytes);
 
 		self.clip_rect = matrix.clip_rect();
 	}
+
+	fn create_bind_group(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, texture: &wgpu::Texture, sampler: &wgpu::Sampler, uniform_buffer: &wgpu::Buffer) -> wgpu::BindGroup
+	{
+		let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+		device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("grid:renderer_bind_group"),
+			layout,
+			entries: &[
+				wgpu::BindGroupEntry {
+					binding: 0,
+					resource: wgpu::BindingResource::TextureView(&texture_view),
+				},
+				wgpu::BindGroupEntry {
+					binding: 1,
+					resource: wgpu::BindingResource::Sampler(sampler),
+				},
+				wgpu::BindGroupEntry {
+					binding: 2,
+					resource: uniform_buffer.as_entire_binding(),
+				},
+			],
+		})
+	}
 }
 
 #[derive(Debug)]
@@ -286,25 +322,19 @@ pub(crate) struct ScalingMatrix {
 }
 
 impl ScalingMatrix {
-    // texture_size is the dimensions of the drawing texture
-    // screen_size is the dimensions of the surface being drawn to
-    pub(crate) fn new(texture_size: (f32, f32), screen_size: (f32, f32)) -> Self {
-        let (texture_width, texture_height) = texture_size;
-        let (screen_width, screen_height) = screen_size;
-
-        // Get smallest scale size
-        let hscale = (screen_width / texture_width).max(1.0).floor();
-		let vscale = (screen_height / texture_height).max(1.0).floor();
+    /// Bildet das Raster auf den Viewport der Zeichenfläche ab.
+    pub(crate) fn new(size: &ScreenSize) -> Self {
+        let viewport = size.viewport();
+        let (screen_width, screen_height) = (size.surface_width as f32, size.surface_height as f32);
 
-        let scaled_width = texture_width * hscale;
-        let scaled_height = texture_height * vscale;
+        let scaled_width = viewport.width * viewport.scale;
+        let scaled_height = viewport.height * viewport.scale;
 
         // Create a transformation matrix
         let sw = scaled_width / screen_width;
         let sh = scaled_height / screen_height;
-        let tx = (texture_width / screen_width - 1.0).max(0.0);
-        let ty = (1.0 - texture_height / screen_height).min(0.0);
-		//let ty = (texture_height / screen_height - 1.0).max(0.0);
+        let tx = (2.0 * viewport.x + scaled_width) / screen_width - 1.0;
+        let ty = 1.0 - (2.0 * viewport.y + scaled_height) / screen_height;
         #[rustfmt::skip]
         let transform: [f32; 16] = [
             sw,  0.0, 0.0, 0.0,
@@ -313,19 +343,9 @@ impl ScalingMatrix {
             tx,  ty, 0.0, 1.0,
         ];
 
-        // Create a clipping rectangle
-        let clip_rect = {
-            let scaled_width = scaled_width.min(screen_width);
-            let scaled_height = scaled_height.min(screen_height);
-            let x = ((screen_width - scaled_width) / 2.0) as u32;
-            let y = ((screen_height - scaled_height) / 2.0) as u32;
-
-            (x, y, scaled_width as u32, scaled_height as u32)
-        };
-
         Self {
             transform: Mat4::from(transform),
-            clip_rect,
+            clip_rect: viewport.clip_rect(),
         }
     }
 
@@ -0,0 +1,488 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::num::NonZeroU32;
+use std::path::Path;
+
+use wgpu::Device;
+use wgpu_glyph::ab_glyph::PxScale;
+
+use super::{Buffer, Color, ScreenSize, WIDE_PLACEHOLDER};
+use super::grid::PixelGrid;
+use super::fonts::FontSet;
+use super::text::{TextGrid, layout_cluster};
+
+const TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+thread_local! {
+	/// Wiederverwendeter Renderer für Offscreen Aufrufe über FFI.
+	static OFFSCREEN: RefCell<Option<HeadlessRenderer>> = const { RefCell::new(None) };
+}
+
+/// Rendert einen Buffer ohne Fenster.
+/// Der Renderer wird pro Thread zwischengespeichert und nur bei neuer Schriftgröße oder Schriftart neu erzeugt.
+/// Schlägt das Rendern fehl, etwa weil das Gerät verloren ging, wird er beim nächsten Aufruf neu erzeugt.
+pub fn render_offscreen(buffer: &Buffer, font_size: u32) -> Result<Image, String>
+{
+	let generation = FontSet::generation();
+	OFFSCREEN.with(|cell| {
+		let mut renderer = cell.borrow_mut();
+		if renderer.as_ref().is_none_or(|r| r.size.font_size != font_size || r.generation != generation)
+		{
+			let size = ScreenSize::from_grid(buffer.width, buffer.height, font_size);
+			renderer.replace(HeadlessRenderer::new(size));
+		}
+		let image = renderer.as_mut().unwrap().render(buffer);
+		if image.is_err()
+		{
+			renderer.take();
+		}
+		image
+	})
+}
+
+/// Rendert einen Buffer immer mit dem CPU Rasterizer.
+/// Das Ergebnis ist unabhängig vom Grafikadapter und eignet sich für Referenzbilder.
+pub fn render_software(buffer: &Buffer, font_size: u32) -> Result<Image, String>
+{
+	let size = ScreenSize::from_grid(buffer.width, buffer.height, font_size);
+	HeadlessRenderer::software(size).render(buffer)
+}
+
+/// RGBA Pixeldaten eines gerenderten Buffers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image
+{
+	pub width: u32,
+	pub height: u32,
+	pub pixels: Vec<u8>,
+}
+
+impl Image
+{
+	pub fn new(width: u32, height: u32) -> Self
+	{
+		Self{ width, height, pixels: vec![0; (width * height * 4) as usize] }
+	}
+
+	#[inline]
+	pub fn pixel(&self, x: u32, y: u32) -> [u8; 4]
+	{
+		let idx = ((y * self.width + x) * 4) as usize;
+		[self.pixels[idx], self.pixels[idx+1], self.pixels[idx+2], self.pixels[idx+3]]
+	}
+
+	/// Speichert das Bild als PNG Datei.
+	pub fn save_png(&self, path: &Path) -> Result<(), Box<dyn Error>>
+	{
+		let file = BufWriter::new(File::create(path)?);
+		let mut encoder = png::Encoder::new(file, self.width, self.height);
+		encoder.set_color(png::ColorType::Rgba);
+		encoder.set_depth(png::BitDepth::Eight);
+		let mut writer = encoder.write_header()?;
+		writer.write_image_data(&self.pixels)?;
+		Ok(())
+	}
+
+	fn fill_rect(&mut self, x: u32, y: u32, w: u32, h: u32, col: [u8; 4])
+	{
+		for yy in y..(y+h).min(self.height)
+		{
+			for xx in x..(x+w).min(self.width)
+			{
+				let idx = ((yy * self.width + xx) * 4) as usize;
+				self.pixels[idx..idx+4].copy_from_slice(&col);
+			}
+		}
+	}
+
+	/// Mischt die Farbe mit der angegebenen Deckkraft in ein Pixel.
+	fn blend(&mut self, x: i32, y: i32, col: Color, coverage: f32)
+	{
+		if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 { return; }
+		let idx = ((y as u32 * self.width + x as u32) * 4) as usize;
+		let a = (coverage * col.a).clamp(0.0, 1.0);
+		let src: [f32; 4] = col.into();
+		for (dst, src) in self.pixels[idx..idx+3].iter_mut().zip(src)
+		{
+			*dst = ((src * a + *dst as f32 / 255.0 * (1.0 - a)) * 255.0).round() as u8;
+		}
+	}
+}
+
+/// FFI Darstellung eines Images. Der Speicher gehört dem Aufrufer und muss mit `free_image` freigegeben werden.
+/// `ptr` ist null, wenn nicht gerendert werden konnte.
+#[repr(C)]
+pub struct C_Image
+{
+	pub width: u32,
+	pub height: u32,
+	pub ptr: *mut u8,
+	pub len: usize,
+}
+
+impl From<Image> for C_Image
+{
+	fn from(image: Image) -> Self
+	{
+		let mut pixels = image.pixels.into_boxed_slice();
+		let len = pixels.len();
+		let ptr = pixels.as_mut_ptr();
+		std::mem::forget(pixels);
+		Self{ width: image.width, height: image.height, ptr, len }
+	}
+}
+
+impl C_Image
+{
+	pub fn error() -> Self
+	{
+		Self{ width: 0, height: 0, ptr: std::ptr::null_mut(), len: 0 }
+	}
+
+	pub unsafe fn free(self)
+	{
+		if !self.ptr.is_null()
+		{
+			drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(self.ptr, self.len)));
+		}
+	}
+}
+
+/// Zeichnet Buffer in eine Offscreen Textur.
+/// Steht kein Grafikadapter zur Verfügung, wird auf einen reinen CPU Rasterizer ausgewichen.
+pub struct HeadlessRenderer
+{
+	size: ScreenSize,
+	generation: u64,
+	target: Target,
+}
+
+enum Target
+{
+	Gpu(Box<GpuTarget>),
+	Cpu(CpuTarget),
+}
+
+impl HeadlessRenderer
+{
+	pub fn new(size: ScreenSize) -> Self
+	{
+		// wgpu erlaubt keine Texturen ohne Fläche. Leere Buffer werden nie an die GPU gegeben.
+		let mut gpu_size = size;
+		gpu_size.resize_grid(size.grid_width.max(1), size.grid_height.max(1));
+		let target = match GpuTarget::new(gpu_size)
+		{
+			Some(gpu) => Target::Gpu(Box::new(gpu)),
+			None => Target::Cpu(CpuTarget::new())
+		};
+		Self{ size, generation: FontSet::generation(), target }
+	}
+
+	/// Erzwingt den CPU Rasterizer.
+	pub fn software(size: ScreenSize) -> Self
+	{
+		let cpu = CpuTarget::new();
+		Self{ size, generation: cpu.fonts.generation, target: Target::Cpu(cpu) }
+	}
+
+	pub fn render(&mut self, buffer: &Buffer) -> Result<Image, String>
+	{
+		if buffer.width != self.size.grid_width || buffer.height != self.size.grid_height
+		{
+			self.size.resize_grid(buffer.width, buffer.height);
+			if let Target::Gpu(gpu) = &mut self.target
+			{
+				if !self.size.is_empty() { gpu.resize(self.size); }
+			}
+		}
+		if self.size.is_empty()
+		{
+			return Ok(Image::new(self.size.window_width, self.size.window_height));
+		}
+
+		match &mut self.target
+		{
+			Target::Gpu(gpu) => gpu.render(buffer),
+			Target::Cpu(cpu) => Ok(cpu.render(buffer, self.size)),
+		}
+	}
+}
+
+struct GpuTarget
+{
+	device: Device,
+	queue: wgpu::Queue,
+	size: ScreenSize,
+	texture: wgpu::Texture,
+	grid: PixelGrid,
+	text: TextGrid,
+}
+
+impl GpuTarget
+{
+	fn new(size: ScreenSize) -> Option<Self>
+	{
+		let instance = wgpu::Instance::new(wgpu::Backends::all());
+
+		let (device, queue) = futures::executor::block_on(async {
+			let mut adapter = instance
+				.request_adapter(&wgpu::RequestAdapterOptions {
+					power_preference: wgpu::PowerPreference::LowPower,
+					compatible_surface: None,
+					force_fallback_adapter: false,
+				})
+				.await;
+			if adapter.is_none()
+			{
+				adapter = instance
+					.request_adapter(&wgpu::RequestAdapterOptions {
+						power_preference: wgpu::PowerPreference::LowPower,
+						compatible_surface: None,
+						force_fallback_adapter: true,
+					})
+					.await;
+			}
+
+			adapter?
+				.request_device(&wgpu::DeviceDescriptor::default(), None)
+				.await
+				.ok()
+		})?;
+
+		let texture = GpuTarget::create_texture(&device, &size);
+		let grid = PixelGrid::new(&device, size, TEXTURE_FORMAT);
+		let text = TextGrid::new(&device, size, TEXTURE_FORMAT);
+
+		Some(Self{ device, queue, size, texture, grid, text })
+	}
+
+	fn create_texture(device: &Device, size: &ScreenSize) -> wgpu::Texture
+	{
+		device.create_texture(&wgpu::TextureDescriptor {
+			label: Some("headless:target_texture"),
+			size: wgpu::Extent3d {
+				width: size.window_width,
+				height: size.window_height,
+				depth_or_array_layers: 1,
+			},
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: wgpu::TextureDimension::D2,
+			format: TEXTURE_FORMAT,
+			usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+		})
+	}
+
+	fn resize(&mut self, size: ScreenSize)
+	{
+		self.size = size;
+		self.texture = GpuTarget::create_texture(&self.device, &size);
+		self.grid.resize(&self.device, size, &self.queue);
+		self.text.resize(&self.device, size);
+	}
+
+	fn render(&mut self, buffer: &Buffer) -> Result<Image, String>
+	{
+		let width = self.size.window_width;
+		let height = self.size.window_height;
+		// Zeilen müssen beim Kopieren auf 256 Bytes ausgerichtet sein.
+		let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+		let padded_row = (width * 4).div_ceil(align) * align;
+
+		let output = self.device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("headless:readback_buffer"),
+			size: (padded_row * height) as wgpu::BufferAddress,
+			usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+			mapped_at_creation: false,
+		});
+
+		let view = &self.texture.create_view(&wgpu::TextureViewDescriptor::default());
+		let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {label: Some("Headless")});
+
+		// Clear frame
+		encoder.begin_render_pass(
+			&wgpu::RenderPassDescriptor {
+				label: Some("Headless pass"),
+				color_attachments: &[
+					wgpu::RenderPassColorAttachment {
+						view,
+						resolve_target: None,
+						ops: wgpu::Operations {
+							load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+							store: true,
+						},
+					},
+				],
+				depth_stencil_attachment: None,
+			},
+		);
+
+		// Der Renderer zeichnet wechselnde Buffer, daher wird immer alles hochgeladen.
+		let dirty = [buffer.bounds()];
+		self.grid.draw_queued(&self.device, &self.queue, &mut encoder, view, buffer, &dirty);
+		self.text.draw(&self.device, &self.queue, &mut encoder, view, buffer, &dirty);
+
+		encoder.copy_texture_to_buffer(
+			wgpu::ImageCopyTexture {
+				texture: &self.texture,
+				mip_level: 0,
+				origin: wgpu::Origin3d::ZERO,
+				aspect: wgpu::TextureAspect::All,
+			},
+			wgpu::ImageCopyBuffer {
+				buffer: &output,
+				layout: wgpu::ImageDataLayout {
+					offset: 0,
+					bytes_per_row: NonZeroU32::new(padded_row),
+					rows_per_image: NonZeroU32::new(height),
+				},
+			},
+			wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+		);
+
+		self.queue.submit(Some(encoder.finish()));
+
+		let slice = output.slice(..);
+		let mapping = slice.map_async(wgpu::MapMode::Read);
+		self.device.poll(wgpu::Maintain::Wait);
+		if let Err(e) = futures::executor::block_on(mapping)
+		{
+			return Err(format!("Could not read rendered image: {}", e));
+		}
+
+		let mut image = Image::new(width, height);
+		{
+			let data = slice.get_mapped_range();
+			let row = (width * 4) as usize;
+			for y in 0..height as usize
+			{
+				let src = y * padded_row as usize;
+				image.pixels[y*row..(y+1)*row].copy_from_slice(&data[src..src+row]);
+			}
+		}
+		output.unmap();
+		Ok(image)
+	}
+}
+
+/// Software Rasterizer als Ausweichlösung für Systeme ohne Grafikadapter.
+struct CpuTarget
+{
+	fonts: FontSet,
+}
+
+impl CpuTarget
+{
+	fn new() -> Self
+	{
+		Self{ fonts: FontSet::current() }
+	}
+
+	fn render(&self, buffer: &Buffer, size: ScreenSize) -> Image
+	{
+		let mut image = Image::new(size.window_width, size.window_height);
+		let cell_size = size.cell_size();
+
+		// Zeichne den Hintergrund
+		for i in 0..buffer.background.len()
+		{
+			let x = (i as u32 % buffer.width) * cell_size.width;
+			let y = (i as u32 / buffer.width) * cell_size.height;
+			image.fill_rect(x, y, cell_size.width, cell_size.height, buffer.background[i].bytes());
+		}
+
+		// Zeichen den Vordergrund
+		let scale = PxScale::from(size.font_size as f32);
+		for i in 0..buffer.chars.len()
+		{
+			let ch = buffer.chars[i];
+			let cluster = buffer.cluster(i);
+			if cluster.is_none() && (ch.is_whitespace() || ch.is_control() || ch == WIDE_PLACEHOLDER) { continue; }
+			let x = ((i as u32 % buffer.width) * cell_size.width) as f32;
+			let y = ((i as u32 / buffer.width) * cell_size.height) as f32;
+
+			let fg = buffer.foreground[i];
+			let mut utf8 = [0; 4];
+			let text = cluster.unwrap_or_else(|| ch.encode_utf8(&mut utf8));
+			for outline in layout_cluster(&self.fonts, text, scale, x, y)
+			{
+				let bounds = outline.px_bounds();
+				outline.draw(|gx, gy, coverage| {
+					image.blend(bounds.min.x as i32 + gx as i32, bounds.min.y as i32 + gy as i32, fg, coverage);
+				});
+			}
+		}
+		image
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	const FONT_SIZE: u32 = 16;
+
+	#[test]
+	fn cpu_target_renders_cells()
+	{
+		let white = Color::new(1.0, 1.0, 1.0, 1.0);
+		let red = Color::new(1.0, 0.0, 0.0, 1.0);
+		let blue = Color::new(0.0, 0.0, 1.0, 1.0);
+		let mut buffer = Buffer::new(2, 1);
+		buffer.write(0, 0, 'W', white, red);
+		buffer.write(1, 0, ' ', white, blue);
+
+		let size = ScreenSize::from_grid(2, 1, FONT_SIZE);
+		let cell = size.cell_size();
+		let image = CpuTarget::new().render(&buffer, size);
+		assert_eq!((image.width, image.height), (2 * cell.width, cell.height));
+
+		// Die rechte Zelle enthält nur Hintergrund.
+		for y in 0..cell.height
+		{
+			for x in cell.width..2*cell.width
+			{
+				assert_eq!(image.pixel(x, y), blue.bytes());
+			}
+		}
+		// Die linke Zelle zeigt Hintergrund und eine Glyphe.
+		let left: Vec<[u8; 4]> = (0..cell.height).flat_map(|y| (0..cell.width).map(move |x| (x, y)))
+			.map(|(x, y)| image.pixel(x, y)).collect();
+		assert!(left.contains(&red.bytes()));
+		assert!(left.iter().any(|p| p[1] > 0));
+	}
+
+	#[test]
+	fn empty_buffers_render_empty_images()
+	{
+		let empty = Buffer::new(0, 0);
+		let image = render_software(&empty, FONT_SIZE).unwrap();
+		assert!(image.pixels.is_empty());
+
+		let mut renderer = HeadlessRenderer::new(ScreenSize::from_grid(0, 0, FONT_SIZE));
+		assert!(renderer.render(&empty).unwrap().pixels.is_empty());
+		let image = renderer.render(&Buffer::new(1, 1)).unwrap();
+		assert_eq!(image.pixels.len(), (image.width * image.height * 4) as usize);
+		assert!(image.width > 0);
+		assert!(renderer.render(&Buffer::new(3, 0)).unwrap().pixels.is_empty());
+	}
+
+	#[test]
+	fn cpu_target_renders_clusters()
+	{
+		let white = Color::new(1.0, 1.0, 1.0, 1.0);
+		let black = Color::new(0.0, 0.0, 0.0, 1.0);
+		let mut plain = Buffer::new(1, 1);
+		plain.writeText(0, 0, "e", white, black);
+		let mut accent = Buffer::new(1, 1);
+		accent.writeText(0, 0, "e\u{301}", white, black);
+
+		let plain = render_software(&plain, FONT_SIZE).unwrap();
+		let accent = render_software(&accent, FONT_SIZE).unwrap();
+		let lit = |img: &Image| img.pixels.chunks_exact(4).filter(|p| p[0] > 0).count();
+		assert!(lit(&accent) > lit(&plain));
+	}
+}
@@ -0,0 +1,137 @@
+use winit::event::VirtualKeyCode;
+
+/// Übersetzt eine winit Taste in den FIN Key Code.
+/// FIN meldet die Windows Virtual-Key Codes, die Unreal an das Spiel weitergibt.
+/// Unbekannte Tasten ergeben 0.
+pub fn key_code(key: VirtualKeyCode) -> i32
+{
+	use VirtualKeyCode::*;
+	match key
+	{
+		Key0 => 0x30, Key1 => 0x31, Key2 => 0x32, Key3 => 0x33, Key4 => 0x34,
+		Key5 => 0x35, Key6 => 0x36, Key7 => 0x37, Key8 => 0x38, Key9 => 0x39,
+
+		A => 0x41, B => 0x42, C => 0x43, D => 0x44, E => 0x45, F => 0x46, G => 0x47,
+		H => 0x48, I => 0x49, J => 0x4A, K => 0x4B, L => 0x4C, M => 0x4D, N => 0x4E,
+		O => 0x4F, P => 0x50, Q => 0x51, R => 0x52, S => 0x53, T => 0x54, U => 0x55,
+		V => 0x56, W => 0x57, X => 0x58, Y => 0x59, Z => 0x5A,
+
+		F1 => 0x70, F2 => 0x71, F3 => 0x72, F4 => 0x73, F5 => 0x74, F6 => 0x75,
+		F7 => 0x76, F8 => 0x77, F9 => 0x78, F10 => 0x79, F11 => 0x7A, F12 => 0x7B,
+		F13 => 0x7C, F14 => 0x7D, F15 => 0x7E, F16 => 0x7F, F17 => 0x80, F18 => 0x81,
+		F19 => 0x82, F20 => 0x83, F21 => 0x84, F22 => 0x85, F23 => 0x86, F24 => 0x87,
+
+		Numpad0 => 0x60, Numpad1 => 0x61, Numpad2 => 0x62, Numpad3 => 0x63, Numpad4 => 0x64,
+		Numpad5 => 0x65, Numpad6 => 0x66, Numpad7 => 0x67, Numpad8 => 0x68, Numpad9 => 0x69,
+		NumpadMultiply => 0x6A,
+		NumpadAdd => 0x6B,
+		NumpadComma => 0x6C,
+		NumpadSubtract => 0x6D,
+		NumpadDecimal => 0x6E,
+		NumpadDivide => 0x6F,
+		NumpadEnter => 0x0D,
+		Numlock => 0x90,
+
+		Back => 0x08,
+		Tab => 0x09,
+		Return => 0x0D,
+		Pause => 0x13,
+		Capital => 0x14,
+		Kana => 0x15,
+		Kanji => 0x19,
+		Escape => 0x1B,
+		Convert => 0x1C,
+		NoConvert => 0x1D,
+		Space => 0x20,
+		PageUp => 0x21,
+		PageDown => 0x22,
+		End => 0x23,
+		Home => 0x24,
+		Left => 0x25,
+		Up => 0x26,
+		Right => 0x27,
+		Down => 0x28,
+		Snapshot => 0x2C,
+		Insert => 0x2D,
+		Delete => 0x2E,
+		LWin => 0x5B,
+		RWin => 0x5C,
+		Apps => 0x5D,
+		Sleep => 0x5F,
+		Scroll => 0x91,
+
+		LShift => 0xA0,
+		RShift => 0xA1,
+		LControl => 0xA2,
+		RControl => 0xA3,
+		LAlt => 0xA4,
+		RAlt => 0xA5,
+
+		WebBack => 0xA6,
+		WebForward => 0xA7,
+		WebRefresh => 0xA8,
+		WebStop => 0xA9,
+		WebSearch => 0xAA,
+		WebFavorites => 0xAB,
+		WebHome => 0xAC,
+		Mute => 0xAD,
+		VolumeDown => 0xAE,
+		VolumeUp => 0xAF,
+		NextTrack => 0xB0,
+		PrevTrack => 0xB1,
+		MediaStop => 0xB2,
+		PlayPause => 0xB3,
+		Mail => 0xB4,
+		MediaSelect => 0xB5,
+
+		Semicolon => 0xBA,
+		Equals | Plus => 0xBB,
+		Comma => 0xBC,
+		Minus => 0xBD,
+		Period => 0xBE,
+		Slash => 0xBF,
+		Grave => 0xC0,
+		LBracket => 0xDB,
+		Backslash => 0xDC,
+		RBracket => 0xDD,
+		Apostrophe => 0xDE,
+		OEM102 => 0xE2,
+
+		_ => 0
+	}
+}
+
+/// Zeichen Code einer Taste, wie ihn FIN als ersten Parameter von OnKeyDown meldet.
+/// Entspricht der Zuordnung eines US Layouts ohne Shift, Buchstaben sind groß.
+/// Tasten ohne Zeichen ergeben 0.
+pub fn key_char(key: VirtualKeyCode) -> i32
+{
+	use VirtualKeyCode::*;
+	let code = key_code(key);
+	match key
+	{
+		Key0 | Key1 | Key2 | Key3 | Key4 | Key5 | Key6 | Key7 | Key8 | Key9 => code,
+		A | B | C | D | E | F | G | H | I | J | K | L | M |
+		N | O | P | Q | R | S | T | U | V | W | X | Y | Z => code,
+		Numpad0 | Numpad1 | Numpad2 | Numpad3 | Numpad4 |
+		Numpad5 | Numpad6 | Numpad7 | Numpad8 | Numpad9 => code - 0x30,
+		Back | Tab | Return | NumpadEnter | Escape | Space => code,
+		NumpadMultiply => '*' as i32,
+		NumpadAdd => '+' as i32,
+		NumpadSubtract => '-' as i32,
+		NumpadDecimal => '.' as i32,
+		NumpadDivide => '/' as i32,
+		Semicolon => ';' as i32,
+		Equals | Plus => '=' as i32,
+		Comma => ',' as i32,
+		Minus => '-' as i32,
+		Period => '.' as i32,
+		Slash => '/' as i32,
+		Grave => '`' as i32,
+		LBracket => '[' as i32,
+		Backslash | OEM102 => '\\' as i32,
+		RBracket => ']' as i32,
+		Apostrophe => '\'' as i32,
+		_ => 0
+	}
+}
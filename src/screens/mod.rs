@@ -1,11 +1,29 @@
+use std::collections::HashMap;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use self::canvas::Frame;
+use self::fonts::FontSet;
+use self::headless::render_offscreen;
+use self::recorder::Recorder;
 use self::screen::ScreenComponent;
 
 
+pub mod canvas;
+pub mod fonts;
+pub mod headless;
+pub mod recorder;
 pub mod renderer;
 pub mod screen;
+pub mod snapshot;
+pub mod storage;
 mod grid;
+mod keys;
+mod shapes;
+mod terminal;
 mod text;
 
 
@@ -50,6 +68,39 @@ impl Color
 		Color{r, g, b, a: 1.0}
 	}
 
+	/// Mischt eine Quellfarbe mit dem FIN Farb Blend Mode in diese Farbe.
+	/// 0 Überschreiben, 1 Behalten, 2 Alpha Blending, 3 Multiplizieren, 4 Dividieren,
+	/// 5 Addieren, 6 Subtrahieren, 7 Differenz, 8 nur Abdunkeln, 9 nur Aufhellen, 10 Keine.
+	/// Alpha Blending ergibt eine deckende Farbe, außer bei 0 bleibt sonst der Alpha Wert des Ziels erhalten.
+	pub fn blend(&self, src: Color, mode: u8) -> Color
+	{
+		match mode
+		{
+			0 => src,
+			2 => self.alphaBlend(src),
+			3 => self.map(src, |d, s| d * s),
+			4 => self.map(src, |d, s| if s <= 0.0 { 1.0 } else { d / s }),
+			5 => self.map(src, |d, s| d + s),
+			6 => self.map(src, |d, s| d - s),
+			7 => self.map(src, |d, s| (d - s).abs()),
+			8 => self.map(src, f32::min),
+			9 => self.map(src, f32::max),
+			_ => *self
+		}
+	}
+
+	#[inline]
+	fn map<F>(&self, src: Color, f: F) -> Color where F: Fn(f32, f32) -> f32
+	{
+		Color
+		{
+			r: f(self.r, src.r).clamp(0.0, 1.0),
+			g: f(self.g, src.g).clamp(0.0, 1.0),
+			b: f(self.b, src.b).clamp(0.0, 1.0),
+			a: self.a
+		}
+	}
+
 	#[inline]
 	pub fn bytes(&self) -> [u8; 4]
 	{
@@ -57,59 +108,224 @@ impl Color
 	}
 }
 
-impl Into<[f32; 4]> for Color
+impl From<Color> for [f32; 4]
 {
 	#[inline]
-    fn into(self) -> [f32; 4]
+    fn from(c: Color) -> [f32; 4]
 	{
-		[self.r, self.g, self.b, self.a]
+		[c.r, c.g, c.b, c.a]
     }
 }
 
+/// Inhalt eines Bildschirms, abhängig vom Typ der gebundenen GPU.
+#[derive(Clone, Debug)]
+pub enum ScreenContent
+{
+	/// Zeichen Matrix einer GPU T1
+	Grid(Arc<Mutex<Buffer>>),
+	/// Zeichenbefehle einer GPU T2
+	Canvas(Arc<Mutex<Frame>>),
+}
+
+impl ScreenContent
+{
+	/// Größe des Inhalts. Zellen bei T1, Pixel bei T2.
+	pub fn dimensions(&self) -> (u32, u32)
+	{
+		match self
+		{
+			ScreenContent::Grid(buffer) => {
+				let b = buffer.lock().unwrap();
+				(b.width, b.height)
+			},
+			ScreenContent::Canvas(frame) => {
+				let f = frame.lock().unwrap();
+				(f.width, f.height)
+			}
+		}
+	}
+}
+
 #[repr(C)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub struct Size
 {
 	pub width: u32,
 	pub height: u32,
 }
 
+/// Rechteckiger Bereich im Zeichenraster.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rect
+{
+	pub x: u32,
+	pub y: u32,
+	pub width: u32,
+	pub height: u32,
+}
+
+impl Rect
+{
+	pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self
+	{
+		Self{ x, y, width, height }
+	}
+
+	#[inline]
+	pub fn area(&self) -> u32
+	{
+		self.width * self.height
+	}
+
+	/// Gemeinsamer Teil beider Bereiche. Ohne Überschneidung ist die Fläche 0.
+	pub fn intersect(&self, other: &Rect) -> Rect
+	{
+		let x = self.x.max(other.x);
+		let y = self.y.max(other.y);
+		let right = (self.x + self.width).min(other.x + other.width).max(x);
+		let bottom = (self.y + self.height).min(other.y + other.height).max(y);
+		Rect{ x, y, width: right - x, height: bottom - y }
+	}
+
+	/// Kleinster Bereich, der beide Bereiche enthält.
+	pub fn union(&self, other: &Rect) -> Rect
+	{
+		let x = self.x.min(other.x);
+		let y = self.y.min(other.y);
+		let right = (self.x + self.width).max(other.x + other.width);
+		let bottom = (self.y + self.height).max(other.y + other.height);
+		Rect{ x, y, width: right - x, height: bottom - y }
+	}
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct ScreenSize
 {
 	pub grid_width: u32,
 	pub grid_height: u32,
+	/// Unskalierte Größe des Inhalts in Pixeln.
 	pub window_width: u32,
 	pub window_height: u32,
+	/// Tatsächliche Größe der Zeichenfläche, z.B. nach Ändern der Fenstergröße.
+	pub surface_width: u32,
+	pub surface_height: u32,
 	pub font_size: u32,
+	/// Zellgröße aus den Metriken der Schriftart.
+	pub cell: Size,
 }
 
 impl ScreenSize
 {
 	pub fn from_grid(width: u32, height: u32, font_size: u32) -> Self
+	{
+		let cell = FontSet::current().cell_size(font_size);
+		Self
+		{
+			grid_width: width,
+			grid_height: height,
+			window_width: width * cell.width,
+			window_height: height * cell.height,
+			surface_width: width * cell.width,
+			surface_height: height * cell.height,
+			font_size,
+			cell
+		}
+	}
+
+	/// Bildschirm einer GPU T2. Das Raster entspricht den Pixeln.
+	pub fn from_pixels(width: u32, height: u32, font_size: u32) -> Self
 	{
 		Self
 		{
 			grid_width: width,
 			grid_height: height,
-			window_width: width * font_size / 2,
-			window_height: height * font_size,
-			font_size
+			window_width: width,
+			window_height: height,
+			surface_width: width,
+			surface_height: height,
+			font_size,
+			cell: Size{width: 1, height: 1}
 		}
 	}
 
 	#[inline]
 	pub fn cell_size(&self) -> Size
 	{
-		Size{width: self.font_size / 2, height: self.font_size}
+		self.cell
 	}
 
 	pub fn resize_grid(&mut self, width: u32, height: u32)
 	{
 		self.grid_width = width;
 		self.grid_height = height;
-		self.window_width = width * self.font_size / 2;
-		self.window_height = height * self.font_size;
+		self.window_width = width * self.cell.width;
+		self.window_height = height * self.cell.height;
+		self.surface_width = self.window_width;
+		self.surface_height = self.window_height;
+	}
+
+	/// Ein Buffer ohne Zellen hat keine Fläche zum Zeichnen.
+	pub fn is_empty(&self) -> bool
+	{
+		self.window_width == 0 || self.window_height == 0
+	}
+
+	pub fn with_surface(mut self, width: u32, height: u32) -> Self
+	{
+		self.surface_width = width;
+		self.surface_height = height;
+		self
+	}
+
+	/// Skaliert den Inhalt mit gleichem Seitenverhältnis in die Zeichenfläche.
+	/// Freie Ränder werden wie im Spiel schwarz dargestellt.
+	pub fn viewport(&self) -> Viewport
+	{
+		let (width, height) = (self.window_width as f32, self.window_height as f32);
+		let (surface_width, surface_height) = (self.surface_width as f32, self.surface_height as f32);
+		let scale = (surface_width / width).min(surface_height / height);
+		Viewport
+		{
+			x: ((surface_width - width * scale) / 2.0).floor(),
+			y: ((surface_height - height * scale) / 2.0).floor(),
+			width,
+			height,
+			scale,
+		}
+	}
+}
+
+/// Position und Skalierung des Inhalts in der Zeichenfläche.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Viewport
+{
+	pub x: f32,
+	pub y: f32,
+	/// Unskalierte Größe des Inhalts
+	pub width: f32,
+	pub height: f32,
+	pub scale: f32,
+}
+
+impl Viewport
+{
+	/// Rechnet eine Position der Zeichenfläche in Pixel des Inhalts um.
+	/// Positionen im Rand ergeben None.
+	pub fn map_position(&self, x: f64, y: f64) -> Option<(f64, f64)>
+	{
+		let cx = (x - self.x as f64) / self.scale as f64;
+		let cy = (y - self.y as f64) / self.scale as f64;
+		if cx < 0.0 || cy < 0.0 || cx >= self.width as f64 || cy >= self.height as f64
+		{
+			return None;
+		}
+		Some((cx, cy))
+	}
+
+	/// Sichtbarer Bereich in der Zeichenfläche: x, y, Breite, Höhe.
+	pub fn clip_rect(&self) -> (u32, u32, u32, u32)
+	{
+		(self.x as u32, self.y as u32, (self.width * self.scale) as u32, (self.height * self.scale) as u32)
 	}
 }
 
@@ -119,7 +335,8 @@ pub struct GraphicHandle
 	pub buffer: Arc<Mutex<Buffer>>,
 	pub screen: Option<ScreenComponent>,
 	pub fg: Color,
-	pub bg: Color
+	pub bg: Color,
+	recorder: Option<Recorder>,
 }
 
 impl GraphicHandle
@@ -132,15 +349,16 @@ impl GraphicHandle
 			screen: None,
 			fg: Color::WHITE,
 			bg: Color::BLACK,
+			recorder: None,
 		}
 	}
 
 	pub fn bind_screen(&mut self, screen: Option<ScreenComponent>)
 	{
 		self.screen = screen;
-		if self.screen.is_some()
+		if let Some(screen) = self.screen.as_mut()
 		{
-			self.screen.as_mut().unwrap().open(self.buffer.clone());
+			screen.open(ScreenContent::Grid(self.buffer.clone()));
 		}
 	}
 
@@ -157,7 +375,6 @@ impl GraphicHandle
 			//self.buffer.lock().unwrap().clone(
 			buffer = b.clone();
 		}
-		let _ = self.buffer.lock().unwrap();
 		buffer
 	}
 
@@ -166,6 +383,12 @@ impl GraphicHandle
 		self.buffer.lock().unwrap().replace(other);
 	}
 
+	/// Anzahl der geänderten Bereiche, die noch nicht gezeichnet wurden.
+	pub fn dirty_count(&self) -> usize
+	{
+		self.buffer.lock().unwrap().dirty_count()
+	}
+
 	pub fn exec<F>(&self, mut func: F) where F: FnMut(&mut Buffer)
 	{
 		func(&mut self.buffer.lock().unwrap());
@@ -173,9 +396,82 @@ impl GraphicHandle
 
 	pub fn flush(&mut self)
 	{
-		if self.screen.is_some()
+		if let Some(screen) = self.screen.as_ref()
+		{
+			screen.flush();
+		}
+		if let Some(recorder) = self.recorder.as_mut()
+		{
+			recorder.capture(&self.buffer.lock().unwrap());
+		}
+	}
+
+	/// Ist ein Bildschirm gebunden, wird dessen Schriftgröße verwendet.
+	fn render_font_size(&self, font_size: u32) -> u32
+	{
+		match &self.screen
+		{
+			Some(screen) => screen.font_size(),
+			None => font_size
+		}
+	}
+
+	/// Rendert den aktuellen Buffer offscreen und speichert ihn als PNG.
+	pub fn screenshot(&self, path: &Path, font_size: u32) -> bool
+	{
+		let font_size = self.render_font_size(font_size);
+		let image = match render_offscreen(&self.buffer.lock().unwrap(), font_size)
+		{
+			Ok(image) => image,
+			Err(e) => {
+				eprintln!("Screenshot Error {}", e);
+				return false;
+			}
+		};
+		match image.save_png(path)
+		{
+			Ok(_) => true,
+			Err(e) => {
+				eprintln!("Screenshot Error {}", e);
+				false
+			}
+		}
+	}
+
+	/// Startet eine Aufnahme, die bei jedem flush ein Bild aufzeichnet.
+	/// Das Format wird über die Dateiendung gewählt: .gif oder .png für APNG.
+	/// Eine laufende Aufnahme wird verworfen.
+	pub fn start_recording(&mut self, path: &Path, font_size: u32) -> bool
+	{
+		match Recorder::new(path, self.render_font_size(font_size))
 		{
-			self.screen.as_ref().unwrap().flush();
+			Ok(recorder) => {
+				self.recorder = Some(recorder);
+				true
+			},
+			Err(e) => {
+				eprintln!("Recording Error {}", e);
+				false
+			}
+		}
+	}
+
+	/// Beendet die Aufnahme und schreibt die Datei.
+	/// Gibt die Anzahl der Bilder zurück oder -1, wenn keine Aufnahme läuft oder ein Fehler auftritt.
+	pub fn stop_recording(&mut self) -> i32
+	{
+		let recorder = match self.recorder.take()
+		{
+			Some(recorder) => recorder,
+			None => return -1
+		};
+		match recorder.finish()
+		{
+			Ok(frames) => frames as i32,
+			Err(e) => {
+				eprintln!("Recording Error {}", e);
+				-1
+			}
 		}
 	}
 }
@@ -188,8 +484,20 @@ pub struct Buffer
 	pub chars: Vec<char>,
 	pub foreground: Vec<Color>,
 	pub background: Vec<Color>,
+	/// Graphem Cluster aus mehreren Zeichen. In chars steht jeweils das erste Zeichen.
+	clusters: HashMap<usize, String>,
+	/// Seit dem letzten Bild geänderte Bereiche
+	dirty: Vec<Rect>,
 }
 
+/// Ab dieser Anzahl werden die geänderten Bereiche zu einem zusammengefasst.
+const MAX_DIRTY_REGIONS: usize = 64;
+/// Belegt die zweite Spalte eines doppelt breiten Zeichens und wird nicht gezeichnet.
+/// Das Nichtzeichen wird beim Schreiben ersetzt und kann daher nicht im Text vorkommen.
+pub const WIDE_PLACEHOLDER: char = '\u{FFFF}';
+/// Maximale Länge eines Graphem Clusters in einer BufferCell.
+pub const MAX_CLUSTER_BYTES: usize = 32;
+
 impl Buffer
 {
 	pub fn new(width: u32, height: u32) -> Buffer
@@ -212,6 +520,45 @@ impl Buffer
 			chars,
 			foreground,
 			background,
+			clusters: HashMap::new(),
+			dirty: vec![Rect::new(0, 0, width, height)],
+		}
+	}
+
+	/// Der gesamte Buffer als Bereich.
+	pub fn bounds(&self) -> Rect
+	{
+		Rect::new(0, 0, self.width, self.height)
+	}
+
+	/// Anzahl der geänderten Bereiche, die noch nicht gezeichnet wurden.
+	pub fn dirty_count(&self) -> usize
+	{
+		self.dirty.len()
+	}
+
+	/// Gibt die geänderten Bereiche zurück und setzt sie zurück.
+	pub fn take_dirty(&mut self) -> Vec<Rect>
+	{
+		std::mem::take(&mut self.dirty)
+	}
+
+	/// Merkt einen geänderten Bereich vor.
+	/// Bereiche werden zusammengefasst, solange dadurch nicht mehr Zellen übertragen werden.
+	fn mark_dirty(&mut self, rect: Rect)
+	{
+		if rect.area() == 0 { return; }
+		let mut rect = rect;
+		while let Some(i) = self.dirty.iter().position(|r| r.union(&rect).area() <= r.area() + rect.area())
+		{
+			rect = rect.union(&self.dirty.swap_remove(i));
+		}
+		self.dirty.push(rect);
+
+		if self.dirty.len() > MAX_DIRTY_REGIONS
+		{
+			let bounds = self.dirty.iter().fold(rect, |a, r| a.union(r));
+			self.dirty = vec![bounds];
 		}
 	}
 
@@ -224,16 +571,21 @@ impl Buffer
 		self.chars.resize(size, char::default());
 		self.foreground.resize(size, Color::WHITE);
 		self.background.resize(size, Color::BLACK);
+		self.clusters.retain(|idx, _| *idx < size);
+		self.dirty = vec![self.bounds()];
 	}
 
+	/// Kopiert einen anderen Buffer an die Position. Teile außerhalb des Ziels werden abgeschnitten.
+	/// Text Blend Modes: 0 Überschreiben, 1 nur mit Zeichen die kein Leerzeichen sind,
+	/// 2 nur Zellen die ein Leerzeichen enthalten, 3 Behalten.
+	/// Die Farb Blend Modes sind bei [`Color::blend`] beschrieben.
 	pub fn copy(&mut self, x: i32, y: i32, other: &Buffer, txtbm: u8, fgbm: u8, bgbm: u8)
 	{
 		let dst_x = x.max(0) as u32;
 		let dst_y = y.max(0) as u32;
-		let mut src_x = 0;
-		let mut src_y = 0;
-		if x < 0 { src_x = (-x) as u32; }
-		if y < 0 { src_y = (-y) as u32; }
+		let src_x = x.min(0).unsigned_abs();
+		let src_y = y.min(0).unsigned_abs();
+		if dst_x >= self.width || dst_y >= self.height || src_x >= other.width || src_y >= other.height { return; }
 		let w = (self.width - dst_x).min(other.width - src_x) as usize;
 		let h = (self.height - dst_y).min(other.height - src_y) as usize;
 
@@ -242,36 +594,64 @@ impl Buffer
 			let src_idx = ((src_y + line) * other.width + src_x) as usize;
 			let dst_idx = ((dst_y + line) * self.width + dst_x) as usize;
 
-			match txtbm
+			for i in 0..w
 			{
-				0 => self.chars[dst_idx..(dst_idx+w)].copy_from_slice(&other.chars[src_idx..(src_idx+w)]),
-				1 => Buffer::copy_chars(
-					&mut self.chars[dst_idx..(dst_idx+w)], 
-					&other.chars[src_idx..(src_idx+w)], 
-					|s, d| match s.is_whitespace() {true => d, false => s}),
-				2 => Buffer::copy_chars(
-					&mut self.chars[dst_idx..(dst_idx+w)], 
-					&other.chars[src_idx..(src_idx+w)], 
-					|s, d| match s.is_whitespace() {true => s, false => d}),
-				_ => {}
+				let (s, d) = (src_idx + i, dst_idx + i);
+				if Buffer::take_text(self.chars[d], other.chars[s], txtbm)
+				{
+					self.chars[d] = other.chars[s];
+					match other.clusters.get(&s)
+					{
+						Some(cluster) => self.clusters.insert(d, cluster.clone()),
+						None => self.clusters.remove(&d)
+					};
+				}
+				self.foreground[d] = self.foreground[d].blend(other.foreground[s], fgbm);
+				self.background[d] = self.background[d].blend(other.background[s], bgbm);
 			}
-			if fgbm < 9
+			// Die Blend Modes entscheiden je Zelle, daher können breite Zeichen an den Rändern
+			// und innerhalb des Bereichs getrennt worden sein. Jede Spalte wird geprüft.
+			for x in dst_x..=(dst_x + w as u32)
 			{
-				self.foreground[dst_idx..(dst_idx+w)].copy_from_slice(&other.foreground[src_idx..(src_idx+w)]);
-			}
-			if bgbm < 9
-			{
-				self.background[dst_idx..(dst_idx+w)].copy_from_slice(&other.background[src_idx..(src_idx+w)]);
+				self.repair_wide(x, dst_y + line);
 			}
 		}
+		self.mark_dirty(Rect::new(dst_x, dst_y, w as u32, h as u32));
 	}
 
+	/// Entfernt die Hälfte eines breiten Zeichens, wenn die Zelle links von x und x nicht zusammenpassen.
+	fn repair_wide(&mut self, x: u32, y: u32)
+	{
+		if x > self.width { return; }
+		let idx = (x + y * self.width) as usize;
+		let lead = x > 0 && self.is_wide(idx - 1);
+		let placeholder = x < self.width && self.chars[idx] == WIDE_PLACEHOLDER;
+		if placeholder && !lead { self.clear_cell(x, y); }
+		if lead && !placeholder { self.clear_cell(x - 1, y); }
+	}
+
+	/// Ob die Zelle ein doppelt breites Zeichen beginnt.
+	fn is_wide(&self, idx: usize) -> bool
+	{
+		match self.clusters.get(&idx)
+		{
+			Some(cluster) => cluster_width(cluster) == 2,
+			None => self.chars[idx] != WIDE_PLACEHOLDER && UnicodeWidthChar::width(self.chars[idx]).unwrap_or(0) >= 2
+		}
+	}
+
+	/// Ob der Text Blend Mode das Zeichen der Quelle übernimmt.
 	#[inline]
-	fn copy_chars<T, F>(dst: &mut [T], src: &[T], map: F) where T: Copy, F: Fn(T, T) -> T
+	fn take_text(dst: char, src: char, mode: u8) -> bool
 	{
-		for i in 0..src.len()
+		// Leere Zellen enthalten das Null Zeichen
+		let blank = |c: char| c == '\0' || c.is_whitespace();
+		match mode
 		{
-			dst[i] = map(src[i], dst[i]);
+			0 => true,
+			1 => !blank(src),
+			2 => blank(dst),
+			_ => false
 		}
 	}
 
@@ -282,10 +662,15 @@ impl Buffer
 		self.chars = other.chars.clone();
 		self.foreground = other.foreground.clone();
 		self.background = other.background.clone();
+		self.clusters = other.clusters.clone();
+		self.dirty = vec![self.bounds()];
 	}
 
+	#[allow(clippy::too_many_arguments)]
 	pub fn fill(&mut self, x: i32, y: i32, w: i32, h: i32, char: char, fg: Color, bg: Color)
 	{
+		let char = if char == WIDE_PLACEHOLDER { char::REPLACEMENT_CHARACTER } else { char };
+		let mut changed = false;
 		for yy in 0..h
 		{
 			let y1 = yy+y;
@@ -296,48 +681,261 @@ impl Buffer
 				let x1 = xx+x;
 				if x1 < 0 { continue; }
 				if x1 as u32 >= self.width { break; }
-				self.write(x1, y1, char, fg, bg);
+				changed |= self.set_cell(x1 as usize + y1 as usize * self.width as usize, char, fg, bg);
 			}
 		}
+		if changed
+		{
+			let (x0, y0) = (x.max(0), y.max(0));
+			let x1 = (x + w).min(self.width as i32);
+			let y1 = (y + h).min(self.height as i32);
+			self.mark_dirty(Rect::new(x0 as u32, y0 as u32, (x1 - x0) as u32, (y1 - y0) as u32));
+		}
 	}
 
+	/// Schreibt Text ab der Position. Jeder Graphem Cluster belegt eine Zelle,
+	/// doppelt breite Zeichen nach East Asian Width zwei.
 	pub fn writeText(&mut self, x: i32, y: i32, text: &str, fg: Color, bg: Color)
 	{
-		for (i, ch) in text.chars().enumerate()
+		let mut cx = x;
+		for cluster in text.graphemes(true)
 		{
-			self.write(x+i as i32, y, ch, fg, bg);
+			cx += self.write_cluster(cx, y, cluster, fg, bg);
 		}
 
 		//println!("Buffer: {},{} -> {}", x, y, text);
 	}
 
+	/// Schreibt Zeichen mit je einer eigenen Vorder- und Hintergrundfarbe.
+	/// Die Anzahl der Farben muss der Anzahl der Graphem Cluster entsprechen, sonst wird nichts geschrieben.
+	pub fn setRaw(&mut self, x: i32, y: i32, text: &str, fg: &[Color], bg: &[Color]) -> bool
+	{
+		let clusters: Vec<&str> = text.graphemes(true).collect();
+		if fg.len() != clusters.len() || bg.len() != clusters.len() { return false; }
+		let mut cx = x;
+		for (i, cluster) in clusters.iter().enumerate()
+		{
+			cx += self.write_cluster(cx, y, cluster, fg[i], bg[i]);
+		}
+		true
+	}
+
 	pub fn write(&mut self, x: i32, y: i32, char: char, fg: Color, bg: Color)
 	{
-		if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 { return; }
-		let idx = x as usize + y as usize * self.width as usize;
-		if self.chars[idx] == char && self.foreground[idx] == fg && self.background[idx] == bg { return; }
+		self.write_cluster(x, y, char.encode_utf8(&mut [0; 4]), fg, bg);
+	}
+
+	/// Schreibt einen Graphem Cluster und gibt die Anzahl belegter Spalten zurück.
+	/// Wird ein breites Zeichen teilweise überschrieben, ersetzt ein Leerzeichen den Rest.
+	fn write_cluster(&mut self, x: i32, y: i32, cluster: &str, fg: Color, bg: Color) -> i32
+	{
+		let replaced;
+		let cluster = match cluster.contains(WIDE_PLACEHOLDER)
+		{
+			true => { replaced = cluster.replace(WIDE_PLACEHOLDER, "\u{FFFD}"); replaced.as_str() },
+			false => cluster
+		};
+		let width = cluster_width(cluster);
+		if y < 0 || y >= self.height as i32 { return width; }
+		for i in 0..width
+		{
+			let cx = x + i;
+			if cx < 0 || cx >= self.width as i32 { continue; }
+			let idx = cx as usize + y as usize * self.width as usize;
+
+			if i == 0 && cx > 0 && self.chars[idx] == WIDE_PLACEHOLDER
+			{
+				self.clear_cell(cx as u32 - 1, y as u32);
+			}
+			if i == width - 1 && cx + 1 < self.width as i32 && self.chars[idx + 1] == WIDE_PLACEHOLDER
+			{
+				self.clear_cell(cx as u32 + 1, y as u32);
+			}
+
+			let changed = match i
+			{
+				0 => self.set_cluster(idx, cluster, fg, bg),
+				_ => self.set_cell(idx, WIDE_PLACEHOLDER, fg, bg)
+			};
+			if changed
+			{
+				self.mark_dirty(Rect::new(cx as u32, y as u32, 1, 1));
+			}
+		}
+		width
+	}
+
+	/// Ersetzt den Inhalt einer Zelle durch ein Leerzeichen, die Farben bleiben erhalten.
+	fn clear_cell(&mut self, x: u32, y: u32)
+	{
+		let idx = (x + y * self.width) as usize;
+		if self.set_cell(idx, ' ', self.foreground[idx], self.background[idx])
+		{
+			self.mark_dirty(Rect::new(x, y, 1, 1));
+		}
+	}
+
+	/// Setzt eine Zelle und meldet, ob sie sich geändert hat.
+	#[inline]
+	fn set_cell(&mut self, idx: usize, char: char, fg: Color, bg: Color) -> bool
+	{
+		let cluster = !self.clusters.is_empty() && self.clusters.remove(&idx).is_some();
+		if !cluster && self.chars[idx] == char && self.foreground[idx] == fg && self.background[idx] == bg { return false; }
 		self.chars[idx] = char;
 		self.foreground[idx] = fg;
 		self.background[idx] = bg;
+		true
+	}
+
+	/// Setzt eine Zelle auf einen Cluster aus mehreren Zeichen.
+	fn set_cluster(&mut self, idx: usize, cluster: &str, fg: Color, bg: Color) -> bool
+	{
+		let mut chars = cluster.chars();
+		let first = chars.next().unwrap_or(' ');
+		if chars.next().is_none() { return self.set_cell(idx, first, fg, bg); }
+
+		if self.clusters.get(&idx).map(String::as_str) == Some(cluster) && self.foreground[idx] == fg && self.background[idx] == bg { return false; }
+		self.chars[idx] = first;
+		self.foreground[idx] = fg;
+		self.background[idx] = bg;
+		self.clusters.insert(idx, cluster.to_string());
+		true
+	}
+
+	/// Cluster einer Zelle, wenn sie mehr als ein Zeichen enthält.
+	#[inline]
+	pub fn cluster(&self, idx: usize) -> Option<&str>
+	{
+		self.clusters.get(&idx).map(String::as_str)
+	}
+
+	/// Vollständiger Text einer Zelle. Die zweite Spalte breiter Zeichen ist leer.
+	pub fn cell_text(&self, idx: usize) -> String
+	{
+		match self.clusters.get(&idx)
+		{
+			Some(cluster) => cluster.clone(),
+			None if self.chars[idx] == WIDE_PLACEHOLDER => String::new(),
+			None => self.chars[idx].to_string()
+		}
 	}
 
-	/*pub fn get_cell(&self, x: u32, y: u32) -> BufferCell
+	/// Zelle mit vollständigem Cluster. Zu lange Cluster werden an einer Zeichengrenze abgeschnitten.
+	pub fn get_cell(&self, x: u32, y: u32) -> BufferCell
 	{
 		let idx = x as usize + y as usize * self.width as usize;
+		let mut char = [0; MAX_CLUSTER_BYTES];
+		let mut len = 0;
+		for ch in self.cell_text(idx).chars()
+		{
+			if len + ch.len_utf8() > MAX_CLUSTER_BYTES { break; }
+			len += ch.encode_utf8(&mut char[len..]).len();
+		}
 		BufferCell
 		{
-			char: self.chars[idx],
-			fg: self.foreground[idx], 
-			bg: self.background[idx], 
+			char,
+			len,
+			fg: self.foreground[idx],
+			bg: self.background[idx],
 		}
-	}*/
+	}
+}
+
+/// Anzahl der Spalten eines Clusters nach East Asian Width, mindestens 1 und höchstens 2.
+#[inline]
+fn cluster_width(cluster: &str) -> i32
+{
+	UnicodeWidthStr::width(cluster).clamp(1, 2) as i32
 }
 
 #[repr(C)]
 pub struct BufferCell
 {
-	pub char: [u8; 4],
+	pub char: [u8; MAX_CLUSTER_BYTES],
 	pub len: usize,
 	pub fg: Color,
 	pub bg: Color,
 }
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	fn text_of(buffer: &Buffer) -> Vec<String>
+	{
+		(0..buffer.chars.len()).map(|i| buffer.cell_text(i)).collect()
+	}
+
+	#[test]
+	fn placeholder_cannot_be_written()
+	{
+		let mut buffer = Buffer::new(4, 1);
+		buffer.writeText(0, 0, "\u{7F}\u{FFFF}", Color::WHITE, Color::BLACK);
+		buffer.fill(2, 0, 1, 1, WIDE_PLACEHOLDER, Color::WHITE, Color::BLACK);
+		assert_eq!(text_of(&buffer)[..3], ["\u{7F}", "\u{FFFD}", "\u{FFFD}"]);
+		assert!(!buffer.chars.contains(&WIDE_PLACEHOLDER));
+
+		buffer.writeText(2, 0, "漢", Color::WHITE, Color::BLACK);
+		assert_eq!(buffer.chars[3], WIDE_PLACEHOLDER);
+		assert_eq!(buffer.cell_text(3), "");
+	}
+
+	#[test]
+	fn copy_repairs_split_wide_chars()
+	{
+		let mut src = Buffer::new(4, 1);
+		src.writeText(0, 0, "漢字", Color::WHITE, Color::BLACK);
+
+		// Die Quelle beginnt mitten in einem breiten Zeichen.
+		let mut dst = Buffer::new(4, 1);
+		dst.copy(-1, 0, &src, 0, 0, 0);
+		assert_eq!(text_of(&dst), [" ", "字", "", "\0"]);
+
+		// Die Quelle endet mitten in einem breiten Zeichen.
+		let mut dst = Buffer::new(4, 1);
+		dst.writeText(0, 0, "abcd", Color::WHITE, Color::BLACK);
+		let mut narrow = Buffer::new(3, 1);
+		narrow.copy(0, 0, &src, 0, 0, 0);
+		dst.copy(1, 0, &narrow, 0, 0, 0);
+		assert_eq!(text_of(&dst), ["a", "漢", "", " "]);
+
+		// Das Ziel enthält breite Zeichen, deren Hälften überschrieben werden.
+		let mut dst = Buffer::new(6, 1);
+		dst.writeText(0, 0, "漢字漢", Color::WHITE, Color::BLACK);
+		let mut ab = Buffer::new(2, 1);
+		ab.writeText(0, 0, "ab", Color::WHITE, Color::BLACK);
+		dst.copy(1, 0, &ab, 0, 0, 0);
+		assert_eq!(text_of(&dst), [" ", "a", "b", " ", "漢", ""]);
+
+		// Text Blend Modes entscheiden je Zelle, getrennte Paare innerhalb des Bereichs werden repariert.
+		let mut wide = Buffer::new(2, 1);
+		wide.writeText(0, 0, "漢", Color::WHITE, Color::BLACK);
+		let text = |dst: &str, src: &Buffer, mode: u8| {
+			let mut buffer = Buffer::new(2, 1);
+			buffer.writeText(0, 0, dst, Color::WHITE, Color::BLACK);
+			buffer.copy(0, 0, src, mode, 0, 0);
+			text_of(&buffer)
+		};
+
+		// Mode 2 übernimmt nur leere Zellen des Ziels, das x bleibt und der Kopf wird entfernt.
+		assert_eq!(text(" x", &wide, 2), [" ", "x"]);
+		assert_eq!(text("x ", &wide, 2), ["x", " "]);
+		assert_eq!(text("  ", &wide, 2), ["漢", ""]);
+
+		// Mode 1 übernimmt nur nicht leere Zellen der Quelle.
+		assert_eq!(text("ab", &wide, 1), ["漢", ""]);
+		let mut a = Buffer::new(2, 1);
+		a.writeText(1, 0, "a", Color::WHITE, Color::BLACK);
+		let mut buffer = Buffer::new(2, 1);
+		buffer.writeText(0, 0, "漢", Color::WHITE, Color::BLACK);
+		buffer.copy(0, 0, &a, 1, 0, 0);
+		assert_eq!(text_of(&buffer), [" ", "a"]);
+
+		// Mode 2 übernimmt nur den Platzhalter, der ohne Kopf entfernt wird.
+		let mut buffer = Buffer::new(2, 1);
+		buffer.writeText(0, 0, "b", Color::WHITE, Color::BLACK);
+		buffer.copy(0, 0, &wide, 2, 0, 0);
+		assert_eq!(text_of(&buffer), ["b", " "]);
+	}
+}
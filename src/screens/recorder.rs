@@ -0,0 +1,233 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use super::Buffer;
+use super::headless::{Image, render_offscreen};
+
+/// Geschwindigkeit der GIF Farbquantisierung von 1 (beste Qualität) bis 30 (schnellste).
+const GIF_QUANTIZE_SPEED: i32 = 10;
+
+/// Ausgabeformat einer Aufnahme, bestimmt durch die Dateiendung.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecordFormat
+{
+	Gif,
+	Apng,
+}
+
+impl RecordFormat
+{
+	pub fn from_path(path: &Path) -> Option<Self>
+	{
+		let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+		match ext.as_str()
+		{
+			"gif" => Some(RecordFormat::Gif),
+			"png" | "apng" => Some(RecordFormat::Apng),
+			_ => None
+		}
+	}
+}
+
+/// Ein aufgenommenes Bild mit dem Zeitpunkt seit Beginn der Aufnahme.
+struct Frame
+{
+	buffer: Buffer,
+	time: Duration,
+}
+
+/// Zeichnet Buffer bei jedem flush auf und speichert sie als Animation.
+/// Während der Aufnahme werden nur Kopien der Buffer gehalten,
+/// gerendert und kodiert wird erst beim Beenden.
+pub struct Recorder
+{
+	path: PathBuf,
+	format: RecordFormat,
+	font_size: u32,
+	start: Instant,
+	frames: Vec<Frame>,
+}
+
+impl Recorder
+{
+	pub fn new(path: &Path, font_size: u32) -> Result<Self, Box<dyn Error>>
+	{
+		let format = RecordFormat::from_path(path).ok_or("Unsupported format, expected .gif or .png")?;
+		Ok(Self{ path: path.to_path_buf(), format, font_size, start: Instant::now(), frames: Vec::new() })
+	}
+
+	pub fn capture(&mut self, buffer: &Buffer)
+	{
+		self.frames.push(Frame{ buffer: buffer.clone(), time: self.start.elapsed() });
+	}
+
+	/// Rendert alle Bilder und schreibt die Datei. Gibt die Anzahl der geschriebenen Bilder zurück.
+	/// Jedes Bild wird bis zum nächsten flush angezeigt, das letzte bis zum Ende der Aufnahme.
+	pub fn finish(self) -> Result<usize, Box<dyn Error>>
+	{
+		if self.frames.is_empty() { return Err("No frames recorded".into()); }
+		let end = self.start.elapsed();
+
+		let mut images = Vec::with_capacity(self.frames.len());
+		let mut delays = Vec::with_capacity(self.frames.len());
+		for (i, frame) in self.frames.iter().enumerate()
+		{
+			let next = self.frames.get(i+1).map_or(end, |f| f.time);
+			images.push(render_offscreen(&frame.buffer, self.font_size)?);
+			delays.push(next - frame.time);
+		}
+
+		// Alle Bilder einer Animation haben die gleiche Größe.
+		let width = images.iter().map(|i| i.width).max().unwrap_or(0);
+		let height = images.iter().map(|i| i.height).max().unwrap_or(0);
+		let images: Vec<Image> = images.into_iter().map(|i| fit(i, width, height)).collect();
+
+		match self.format
+		{
+			RecordFormat::Gif => write_gif(&self.path, &images, &delays),
+			RecordFormat::Apng => write_apng(&self.path, &images, &delays),
+		}
+	}
+}
+
+/// Schreibt ein GIF und gibt die Anzahl der geschriebenen Bilder zurück.
+fn write_gif(path: &Path, images: &[Image], delays: &[Duration]) -> Result<usize, Box<dyn Error>>
+{
+	let width = u16::try_from(images[0].width)?;
+	let height = u16::try_from(images[0].height)?;
+	let file = BufWriter::new(File::create(path)?);
+	let mut encoder = gif::Encoder::new(file, width, height, &[])?;
+	encoder.set_repeat(gif::Repeat::Infinite)?;
+
+	// GIF Verzögerungen haben eine Auflösung von 10ms.
+	// Gerundet wird auf der Zeitachse, damit sich Rundungsfehler nicht aufsummieren.
+	let mut time = Duration::ZERO;
+	let mut shown = 0u64;
+	let mut written = 0;
+	for (i, (image, delay)) in images.iter().zip(delays).enumerate()
+	{
+		time += *delay;
+		let until = (time.as_millis() as u64 + 5) / 10;
+		let mut centis = until.saturating_sub(shown);
+		// Bilder unter 10ms werden vom nächsten Bild verdeckt, das letzte Bild bleibt immer erhalten.
+		if centis == 0
+		{
+			if i + 1 < images.len() { continue; }
+			centis = 1;
+		}
+		shown = until;
+
+		let mut pixels = image.pixels.clone();
+		let mut frame = gif::Frame::from_rgba_speed(width, height, &mut pixels, GIF_QUANTIZE_SPEED);
+		frame.delay = centis.min(u16::MAX as u64) as u16;
+		encoder.write_frame(&frame)?;
+		written += 1;
+	}
+	Ok(written)
+}
+
+/// Schreibt ein APNG und gibt die Anzahl der geschriebenen Bilder zurück.
+fn write_apng(path: &Path, images: &[Image], delays: &[Duration]) -> Result<usize, Box<dyn Error>>
+{
+	let file = BufWriter::new(File::create(path)?);
+	let mut encoder = png::Encoder::new(file, images[0].width, images[0].height);
+	encoder.set_color(png::ColorType::Rgba);
+	encoder.set_depth(png::BitDepth::Eight);
+	encoder.set_animated(images.len() as u32, 0)?;
+	let mut writer = encoder.write_header()?;
+	for (image, delay) in images.iter().zip(delays)
+	{
+		let (num, den) = apng_delay(*delay);
+		writer.set_frame_delay(num, den)?;
+		writer.write_image_data(&image.pixels)?;
+	}
+	writer.finish()?;
+	Ok(images.len())
+}
+
+/// APNG Verzögerungen sind Brüche aus 16 Bit Werten.
+/// Lange Verzögerungen verwenden einen gröberen Nenner statt abgeschnitten zu werden.
+fn apng_delay(delay: Duration) -> (u16, u16)
+{
+	let millis = delay.as_millis();
+	for den in [1000u16, 100, 10, 1]
+	{
+		let num = (millis * den as u128 + 500) / 1000;
+		if num <= u16::MAX as u128 { return (num as u16, den); }
+	}
+	(u16::MAX, 1)
+}
+
+/// Vergrößert ein Bild auf die angegebene Größe. Neue Pixel sind schwarz.
+fn fit(image: Image, width: u32, height: u32) -> Image
+{
+	if image.width == width && image.height == height { return image; }
+	let mut result = Image::new(width, height);
+	let row = (image.width * 4) as usize;
+	for y in 0..image.height as usize
+	{
+		let dst = y * (width * 4) as usize;
+		result.pixels[dst..dst+row].copy_from_slice(&image.pixels[y*row..(y+1)*row]);
+	}
+	for alpha in result.pixels.iter_mut().skip(3).step_by(4)
+	{
+		*alpha = 255;
+	}
+	result
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	fn temp_path(name: &str) -> PathBuf
+	{
+		std::env::temp_dir().join(format!("freen-{}-{}", std::process::id(), name))
+	}
+
+	fn gif_delays(path: &Path) -> Vec<u16>
+	{
+		let mut options = gif::DecodeOptions::new();
+		options.set_color_output(gif::ColorOutput::RGBA);
+		let mut decoder = options.read_info(File::open(path).unwrap()).unwrap();
+		let mut delays = Vec::new();
+		while let Some(frame) = decoder.read_next_frame().unwrap()
+		{
+			delays.push(frame.delay);
+		}
+		delays
+	}
+
+	#[test]
+	fn gif_counts_written_frames()
+	{
+		let images = vec![Image::new(2, 2), Image::new(2, 2), Image::new(2, 2)];
+		let path = temp_path("frames.gif");
+
+		// Kurze Bilder werden vom nächsten verdeckt.
+		let delays = [Duration::from_millis(2), Duration::from_millis(30), Duration::from_millis(20)];
+		assert_eq!(write_gif(&path, &images, &delays).unwrap(), 2);
+		assert_eq!(gif_delays(&path), [3, 2]);
+
+		// Das letzte Bild bleibt auch dann erhalten, wenn alle Zeiten auf 0 fallen.
+		let delays = [Duration::from_millis(1); 3];
+		assert_eq!(write_gif(&path, &images, &delays).unwrap(), 1);
+		assert_eq!(gif_delays(&path), [1]);
+		std::fs::remove_file(&path).unwrap();
+	}
+
+	#[test]
+	fn long_apng_delays_use_coarser_fractions()
+	{
+		assert_eq!(apng_delay(Duration::from_millis(1500)), (1500, 1000));
+		assert_eq!(apng_delay(Duration::from_millis(65_535)), (65_535, 1000));
+		assert_eq!(apng_delay(Duration::from_secs(100)), (10_000, 100));
+		assert_eq!(apng_delay(Duration::from_secs(3600)), (36_000, 10));
+		assert_eq!(apng_delay(Duration::from_secs(7200)), (7_200, 1));
+		assert_eq!(apng_delay(Duration::from_secs(100_000)), (u16::MAX, 1));
+	}
+}
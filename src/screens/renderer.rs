@@ -4,11 +4,20 @@
 use wgpu::SurfaceError;
 use winit::window::Window;
 
-use super::ScreenSize;
-use super::{grid::PixelGrid, Buffer};
-
+use super::{ScreenContent, ScreenSize};
+use super::grid::PixelGrid;
+use super::shapes::ShapeRenderer;
 use super::text::TextGrid;
 
+/// Zeichenebenen für den jeweiligen Bildschirminhalt.
+/// Pro Fenster gibt es genau eine Instanz, die Größe der Varianten spielt keine Rolle.
+#[allow(clippy::large_enum_variant)]
+enum Layers
+{
+	Grid(PixelGrid, TextGrid),
+	Canvas(ShapeRenderer),
+}
+
 pub struct Renderer
 {
 	device: wgpu::Device,
@@ -16,13 +25,14 @@ pub struct Renderer
 	surface: wgpu::Surface,
 	surface_format: wgpu::TextureFormat,
 	present_mode: wgpu::PresentMode,
-	grid: PixelGrid,
-	text: TextGrid,
+	layers: Layers,
+	/// Anzahl der im letzten Bild hochgeladenen Bereiche
+	dirty_count: usize,
 }
 
 impl Renderer
 {
-	pub fn new(window: &Window, size: ScreenSize, present_mode: wgpu::PresentMode) -> Self
+	pub fn new(window: &Window, size: ScreenSize, present_mode: wgpu::PresentMode, content: &ScreenContent) -> Self
 	{
 		let instance = wgpu::Instance::new(wgpu::Backends::all());
 		let surface = unsafe { instance.create_surface(&window) };
@@ -45,20 +55,25 @@ impl Renderer
 		});
 
 		let surface_format = wgpu::TextureFormat::Bgra8Unorm;
-		let grid = PixelGrid::new(&device, size, surface_format);
 
 		surface.configure(
 			&device,
 			&wgpu::SurfaceConfiguration {
 				usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
 				format: surface_format,
-				width: size.window_width,
-				height: size.window_height,
+				width: size.surface_width,
+				height: size.surface_height,
 				present_mode,
 			},
 		);
 
-		let text = TextGrid::new(&device, size, surface_format);
+		let layers = match content
+		{
+			ScreenContent::Grid(_) => Layers::Grid(
+				PixelGrid::new(&device, size, surface_format),
+				TextGrid::new(&device, size, surface_format)),
+			ScreenContent::Canvas(_) => Layers::Canvas(ShapeRenderer::new(&device, size, surface_format)),
+		};
 
 		Self{
 			device,
@@ -66,8 +81,8 @@ impl Renderer
 			surface,
 			surface_format,
 			present_mode,
-			grid,
-			text
+			layers,
+			dirty_count: 0,
 		}
 	}
 
@@ -78,19 +93,31 @@ impl Renderer
 			&wgpu::SurfaceConfiguration {
 				usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
 				format: self.surface_format,
-				width: size.window_width,
-				height: size.window_height,
+				width: size.surface_width,
+				height: size.surface_height,
 				present_mode: self.present_mode,
 			},
 		);
 
-		self.grid.resize(&self.device, size, &self.queue);
-		self.text.resize(&self.device, size);
+		match &mut self.layers
+		{
+			Layers::Grid(grid, text) => {
+				grid.resize(&self.device, size, &self.queue);
+				text.resize(&self.device, size);
+			},
+			Layers::Canvas(shapes) => shapes.resize(size, &self.queue),
+		}
+	}
+
+	/// Anzahl der im letzten Bild hochgeladenen Bereiche, für Profiling.
+	pub fn dirty_count(&self) -> usize
+	{
+		self.dirty_count
 	}
 
-	pub fn render(&mut self, buffer: &Buffer) -> bool
+	pub fn render(&mut self, content: &ScreenContent) -> bool
 	{
-		let result = self.render_frame(buffer);
+		let result = self.render_frame(content);
 		match result
 		{
 			Ok(_) => false,
@@ -98,7 +125,7 @@ impl Renderer
 		}
 	}
 
-	fn render_frame(&mut self, buffer: &Buffer) -> Result<bool, SurfaceError>
+	fn render_frame(&mut self, content: &ScreenContent) -> Result<bool, SurfaceError>
 	{
 		let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {label: Some("Redraw")});
 
@@ -124,16 +151,31 @@ impl Renderer
 			},
 		);
 
-		// Zeichne den Hintergrund
-		self.grid.draw_queued(&self.device, &self.queue, &mut encoder, view, buffer);
-		// Zeichen den Vordergrund
-		self.text.draw(&self.device, &mut encoder, view, buffer);
-		//self.grid.draw_queued(&self.device, &self.queue, &mut encoder, view, buffer);
+		match (&mut self.layers, content)
+		{
+			(Layers::Grid(grid, text), ScreenContent::Grid(buffer)) => {
+				let mut buffer = buffer.lock().unwrap();
+				let dirty = buffer.take_dirty();
+				self.dirty_count = dirty.len();
+				// Zeichne den Hintergrund
+				grid.draw_queued(&self.device, &self.queue, &mut encoder, view, &buffer, &dirty);
+				// Zeichen den Vordergrund
+				text.draw(&self.device, &self.queue, &mut encoder, view, &buffer, &dirty);
+				//self.grid.draw_queued(&self.device, &self.queue, &mut encoder, view, buffer);
+			},
+			(Layers::Canvas(shapes), ScreenContent::Canvas(canvas)) => {
+				shapes.draw(&self.device, &mut encoder, view, &canvas.lock().unwrap());
+			},
+			_ => {}
+		}
 
 		self.queue.submit(Some(encoder.finish()));
 		frame.present();
-		
-		self.text.clean_frame();
+
+		if let Layers::Canvas(shapes) = &mut self.layers
+		{
+			shapes.clean_frame();
+		}
 
 		Ok(false)
 	}
@@ -1,27 +1,145 @@
 
 use crate::*;
-use super::{Buffer, Color, ScreenSize};
+use super::{Color, ScreenContent, ScreenSize, Size, Viewport};
+use super::keys::{key_char, key_code};
 use super::renderer::Renderer;
+use super::terminal::{TerminalCommand, TerminalScreen};
 
-use std::{sync::{Arc, Mutex}, thread, fmt::Debug};
+use std::{sync::{Arc, Mutex, mpsc}, thread, fmt::Debug};
 use fps_counter::FPSCounter;
-use winit::{platform::windows::EventLoopExtWindows, dpi::PhysicalPosition};
-use winit::window::Icon;
+use winit::dpi::PhysicalPosition;
+use winit::monitor::{MonitorHandle, VideoMode};
+use winit::window::{Fullscreen, Icon};
 use winit::{
 	event,
     event_loop::{ControlFlow, EventLoop},
     window::{WindowBuilder, Window}, dpi::PhysicalSize,
 	platform::run_return::EventLoopExtRunReturn
 };
-use winit::event::{WindowEvent, ElementState, MouseButton};
+use winit::event::{WindowEvent, ElementState, MouseButton, MouseScrollDelta};
 
 
-const EVENT_WINDOW_CLOSED: &str = "WindowClosed\0";
+pub(super) const EVENT_WINDOW_CLOSED: &str = "WindowClosed\0";
 const EVENT_MOUSE_DOWN: &str = "OnMouseDown\0";
 const EVENT_MOUSE_UP: &str = "OnMouseUp\0";
 const EVENT_MOUSE_MOVE: &str = "OnMouseMove\0";
-const EVENT_KEY_DOWN: &str = "OnKeyDown\0";
-const EVENT_KEY_UP: &str = "OnKeyUp\0";
+const EVENT_MOUSE_WHEEL: &str = "OnMouseWheel\0";
+pub(super) const EVENT_KEY_DOWN: &str = "OnKeyDown\0";
+pub(super) const EVENT_KEY_UP: &str = "OnKeyUp\0";
+pub(super) const EVENT_KEY_CHAR: &str = "OnKeyChar\0";
+
+/// Vollbild Modus eines Fensters.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum FullscreenMode
+{
+	#[default]
+	Windowed,
+	/// Randloses Fenster in Bildschirmgröße
+	Borderless,
+	/// Exklusiver Vollbild Modus mit der höchsten Auflösung des Monitors
+	Exclusive,
+}
+
+impl FullscreenMode
+{
+	pub fn from_u8(mode: u8) -> Self
+	{
+		match mode
+		{
+			1 => FullscreenMode::Borderless,
+			2 => FullscreenMode::Exclusive,
+			_ => FullscreenMode::Windowed,
+		}
+	}
+}
+
+/// Ausgabe eines Bildschirms.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Backend
+{
+	#[default]
+	Window,
+	/// Ausgabe im aktuellen Terminal mit ANSI Farben. Nur für GPU T1.
+	Terminal,
+}
+
+impl Backend
+{
+	pub fn from_u8(backend: u8) -> Self
+	{
+		match backend
+		{
+			1 => Backend::Terminal,
+			_ => Backend::Window,
+		}
+	}
+}
+
+/// Monitor und Vollbild Modus eines Fensters.
+/// Wird beim Öffnen des Fensters angewendet und bleibt bei erneutem Öffnen erhalten.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Placement
+{
+	/// Index des Monitors, None für den aktuellen Monitor
+	pub monitor: Option<usize>,
+	pub fullscreen: FullscreenMode,
+}
+
+/// Position und Größe eines Monitors in physischen Pixeln.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct MonitorInfo
+{
+	pub x: i32,
+	pub y: i32,
+	pub width: u32,
+	pub height: u32,
+	pub scale_factor: f64,
+}
+
+impl From<MonitorHandle> for MonitorInfo
+{
+	fn from(monitor: MonitorHandle) -> Self
+	{
+		let position = monitor.position();
+		let size = monitor.size();
+		Self{ x: position.x, y: position.y, width: size.width, height: size.height, scale_factor: monitor.scale_factor() }
+	}
+}
+
+/// Liste der Monitore für Lua. `ptr` ist null, wenn die Monitore nicht abgefragt werden konnten.
+#[repr(C)]
+pub struct C_Monitors
+{
+	pub ptr: *mut MonitorInfo,
+	pub len: usize,
+}
+
+impl From<Vec<MonitorInfo>> for C_Monitors
+{
+	fn from(monitors: Vec<MonitorInfo>) -> Self
+	{
+		let len = monitors.len();
+		let ptr = Box::into_raw(monitors.into_boxed_slice()) as *mut MonitorInfo;
+		Self{ ptr, len }
+	}
+}
+
+impl C_Monitors
+{
+	pub fn error() -> Self
+	{
+		Self{ ptr: std::ptr::null_mut(), len: 0 }
+	}
+
+	pub unsafe fn free(self)
+	{
+		if !self.ptr.is_null()
+		{
+			drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(self.ptr, self.len)));
+		}
+	}
+}
 
 pub struct ScreenComponent
 {
@@ -30,6 +148,10 @@ pub struct ScreenComponent
 	pub bg: Color,
 	font_size: u32,
 	window: Arc<Mutex<Option<Window>>>,
+	placement: Arc<Mutex<Placement>>,
+	backend: Backend,
+	/// Befehle an den Terminal Thread, solange das Terminal geöffnet ist.
+	terminal: Arc<Mutex<Option<mpsc::Sender<TerminalCommand>>>>,
 	emitter: Option<EventEmitter>
 }
 
@@ -44,27 +166,83 @@ impl ScreenComponent
 			bg: Color::BLACK,
 			font_size,
 			window: Arc::new( Mutex::new(None)),
+			placement: Arc::new( Mutex::new(Placement::default())),
+			backend: Backend::default(),
+			terminal: Arc::new( Mutex::new(None)),
 			emitter: None
 		}
 	}
 
+	/// Weitere Referenz auf denselben Bildschirm, z.B. für die gebundene GPU.
+	/// Fenster und Platzierung werden geteilt, der Lua Handle bleibt gültig.
+	pub fn share(&self) -> Self
+	{
+		Self
+		{
+			id: self.id,
+			fg: self.fg,
+			bg: self.bg,
+			font_size: self.font_size,
+			window: self.window.clone(),
+			placement: self.placement.clone(),
+			backend: self.backend,
+			terminal: self.terminal.clone(),
+			emitter: self.emitter.clone()
+		}
+	}
+
 	pub fn set_emitter(&mut self, emitter: Option<EventEmitter>)
 	{
 		self.emitter = emitter;
 	}
 
-	pub fn open(&mut self, buffer: Arc<Mutex<Buffer>>)
+	/// Wählt die Ausgabe. Wird beim nächsten Öffnen angewendet.
+	pub fn set_backend(&mut self, backend: Backend)
+	{
+		self.backend = backend;
+	}
+
+	pub fn open(&mut self, content: ScreenContent)
 	{
+		if self.backend == Backend::Terminal
+		{
+			match &content
+			{
+				ScreenContent::Grid(buffer) => return self.open_terminal(buffer.clone()),
+				ScreenContent::Canvas(_) => eprintln!("Terminal Error GPU T2 is not supported, opening a window"),
+			}
+		}
+		if !WINDOW_THREAD_SUPPORTED
+		{
+			eprintln!("Screen Error windows are not supported on this platform, use FREEN.backend = \"terminal\"");
+			return;
+		}
 		let font_size = self.font_size;
 		let emitter = self.emitter.take();
-		let buffer_arc = buffer.clone();
 		let window_arc = self.window.clone();
+		let placement = *self.placement.lock().unwrap();
 		thread::spawn(move || {
-			let (mut screen, event_loop) = Screen::new(font_size, emitter, buffer_arc, window_arc);
+			let (mut screen, event_loop) = Screen::new(font_size, emitter, content, window_arc, placement);
 			screen.run_event_loop(event_loop);
 		});
 	}
 
+	fn open_terminal(&mut self, buffer: Arc<Mutex<super::Buffer>>)
+	{
+		let (sender, receiver) = mpsc::channel();
+		// Ein bereits geöffnetes Terminal wird ersetzt.
+		if let Some(old) = self.terminal.lock().unwrap().replace(sender)
+		{
+			let _ = old.send(TerminalCommand::Close);
+		}
+		let emitter = self.emitter.take();
+		let terminal_arc = self.terminal.clone();
+		thread::spawn(move || {
+			TerminalScreen::new(buffer, emitter).run(receiver);
+			terminal_arc.lock().unwrap().take();
+		});
+	}
+
 	pub fn flush(&self)
 	{
 		let window = self.window.lock().unwrap();
@@ -72,28 +250,127 @@ impl ScreenComponent
 		{
 			window.as_ref().unwrap().request_redraw();
 		}
+		if let Some(terminal) = self.terminal.lock().unwrap().as_ref()
+		{
+			let _ = terminal.send(TerminalCommand::Redraw);
+		}
+	}
+
+	/// Beendet die Terminal Ausgabe und stellt das Terminal wieder her.
+	/// Fenster bleiben geöffnet, bis sie vom Benutzer geschlossen werden.
+	pub fn close(&self)
+	{
+		if let Some(terminal) = self.terminal.lock().unwrap().take()
+		{
+			let _ = terminal.send(TerminalCommand::Close);
+		}
+	}
+
+	pub fn font_size(&self) -> u32
+	{
+		self.font_size
 	}
 
 	pub fn isOpen(&self) -> bool
 	{
-		self.window.lock().unwrap().is_some()
+		self.window.lock().unwrap().is_some() || self.terminal.lock().unwrap().is_some()
 	}
 
 	pub fn screen_location(&mut self, x: i32, y: i32)
 	{
-		match self.window.lock().unwrap().as_ref()
+		if let Some(w) = self.window.lock().unwrap().as_ref()
 		{
-			Some(w) => w.set_outer_position(PhysicalPosition{x, y}),
-			None => {}
+			w.set_outer_position(PhysicalPosition{x, y});
 		}
 	}
+
+	/// Verschiebt das Fenster auf einen Monitor. Ist das Fenster noch nicht geöffnet,
+	/// wird der Monitor beim Öffnen verwendet.
+	pub fn screen_monitor(&mut self, monitor: Option<usize>)
+	{
+		let placement = {
+			let mut placement = self.placement.lock().unwrap();
+			placement.monitor = monitor;
+			*placement
+		};
+		if let Some(w) = self.window.lock().unwrap().as_ref()
+		{
+			apply_placement(w, &placement);
+		}
+	}
+
+	pub fn screen_fullscreen(&mut self, mode: FullscreenMode)
+	{
+		let placement = {
+			let mut placement = self.placement.lock().unwrap();
+			placement.fullscreen = mode;
+			*placement
+		};
+		if let Some(w) = self.window.lock().unwrap().as_ref()
+		{
+			apply_placement(w, &placement);
+		}
+	}
+
+	/// Alle verfügbaren Monitore. Ohne geöffnetes Fenster wird dafür kurz eine eigene Event Loop erzeugt,
+	/// damit ein Monitor schon vor dem Öffnen gewählt werden kann.
+	pub fn monitors(&self) -> Result<Vec<MonitorInfo>, String>
+	{
+		if let Some(w) = self.window.lock().unwrap().as_ref()
+		{
+			return Ok(w.available_monitors().map(MonitorInfo::from).collect());
+		}
+		if !WINDOW_THREAD_SUPPORTED { return Err("monitors are not available on this platform".to_string()); }
+		if !display_available() { return Err("no display available".to_string()); }
+		let event_loop = create_event_loop();
+		let monitors = event_loop.available_monitors().map(MonitorInfo::from).collect();
+		Ok(monitors)
+	}
+}
+
+/// Wendet Monitor und Vollbild Modus auf ein Fenster an.
+fn apply_placement(window: &Window, placement: &Placement)
+{
+	let monitor = placement.monitor
+		.and_then(|i| window.available_monitors().nth(i))
+		.or_else(|| window.current_monitor());
+
+	match placement.fullscreen
+	{
+		FullscreenMode::Windowed => {
+			window.set_fullscreen(None);
+			if let (Some(_), Some(m)) = (placement.monitor, monitor)
+			{
+				window.set_outer_position(m.position());
+			}
+		},
+		FullscreenMode::Borderless => window.set_fullscreen(Some(Fullscreen::Borderless(monitor))),
+		FullscreenMode::Exclusive => {
+			// Ohne passenden Video Modus wird randlos dargestellt.
+			let fullscreen = match monitor.as_ref().and_then(best_video_mode)
+			{
+				Some(mode) => Fullscreen::Exclusive(mode),
+				None => Fullscreen::Borderless(monitor),
+			};
+			window.set_fullscreen(Some(fullscreen));
+		}
+	}
+}
+
+/// Höchste Auflösung mit der höchsten Bildwiederholrate.
+fn best_video_mode(monitor: &MonitorHandle) -> Option<VideoMode>
+{
+	monitor.video_modes().max_by_key(|m| {
+		let size = m.size();
+		(size.width * size.height, m.refresh_rate(), m.bit_depth())
+	})
 }
 
 struct Screen
 {
 	size: ScreenSize,
 	input: InputHelper,
-	buffer: Arc<Mutex<Buffer>>,
+	content: ScreenContent,
 	window: Arc<Mutex<Option<Window>>>,
 	title: String,
 	renderer: Renderer,
@@ -104,46 +381,47 @@ impl Debug for Screen
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
 	{
         f.debug_struct("Screen").field("size", &self.size)
-		.field("buffer", &self.buffer)
+		.field("content", &self.content)
 		.field("window", &self.window).finish()
     }
 }
 
 impl Screen
 {
-	fn new(font_size: u32, emitter: Option<EventEmitter>, buffer: Arc<Mutex<Buffer>>, window_arc: Arc<Mutex<Option<Window>>>) -> (Self, EventLoop<()>)
+	fn new(font_size: u32, emitter: Option<EventEmitter>, content: ScreenContent, window_arc: Arc<Mutex<Option<Window>>>, placement: Placement) -> (Self, EventLoop<()>)
 	{
-		let buf = buffer.lock().unwrap();
-		let width = buf.width;
-		let height = buf.height;
-		drop(buf);
-		let input = InputHelper::new(emitter, width, height);
-
-		let size = ScreenSize::from_grid(width, height, font_size);
+		let (width, height) = content.dimensions();
+		let size = match content
+		{
+			ScreenContent::Grid(_) => ScreenSize::from_grid(width, height, font_size),
+			ScreenContent::Canvas(_) => ScreenSize::from_pixels(width, height, font_size),
+		};
+		let input = InputHelper::new(emitter, InputMode::new(&content, &size), size.viewport());
 
 		let iconData = include_bytes!("icon.rgba");
 		let icon = Icon::from_rgba(iconData.to_vec(), 32, 32).ok();
 
-		let event_loop: EventLoop<()> = EventLoop::new_any_thread();
+		let event_loop: EventLoop<()> = create_event_loop();
 
 		let title = "Screen".to_string();
 		let window = WindowBuilder::new()
 		.with_title(&title)
 		.with_inner_size(PhysicalSize{width: size.window_width as f32, height: size.window_height as f32})
 		.with_maximized(false)
-		.with_resizable(false)
+		.with_resizable(true)
 		.with_window_icon(icon)
 		.build(&event_loop)
 		.unwrap();
+		apply_placement(&window, &placement);
 
-		let renderer = Renderer::new( &window, size, wgpu::PresentMode::Mailbox);
+		let renderer = Renderer::new( &window, size, wgpu::PresentMode::Mailbox, &content);
 		window_arc.lock().unwrap().replace(window);
 
 		(Self
 		{
 			size,
 			input,
-			buffer,
+			content,
 			window: window_arc,
 			title,
 			renderer
@@ -156,15 +434,24 @@ impl Screen
 
 		event_loop.run_return(|event, _, control_flow| {
 
+			if let event::Event::WindowEvent{event: WindowEvent::Resized(size), ..} = event
+			{
+				self.resize_surface(size.width, size.height);
+			}
+
 			if let event::Event::RedrawRequested(_) = event
 			{
 				self.perfom_resizeing();
-				if self.renderer.render(&self.buffer.lock().unwrap())
+				if self.renderer.render(&self.content)
 				{
 					*control_flow = ControlFlow::Exit;
 					return;
 				}
-				self.title = format!("Screen {} fps", &fps_counter.tick());
+				self.title = match self.content
+				{
+					ScreenContent::Grid(_) => format!("Screen {} fps, {} dirty", &fps_counter.tick(), self.renderer.dirty_count()),
+					ScreenContent::Canvas(_) => format!("Screen {} fps", &fps_counter.tick()),
+				};
 				self.window.lock().unwrap().as_mut().unwrap().set_title(&self.title);
 			}
 	
@@ -172,7 +459,6 @@ impl Screen
 			if self.input.closeRequested()
 			{
 				*control_flow = ControlFlow::Exit;
-				return;
 			}
 		});
 		self.window.lock().unwrap().take();
@@ -180,15 +466,33 @@ impl Screen
 
 	fn perfom_resizeing(&mut self)
 	{
-		let buffer = self.buffer.lock().unwrap();
-		if buffer.width != self.size.grid_width || buffer.height != self.size.grid_height
+		let (width, height) = self.content.dimensions();
+		if width != self.size.grid_width || height != self.size.grid_height
 		{
-			self.size.resize_grid(buffer.width, buffer.height);
+			// Die Zeichenfläche behält ihre Größe, bis das Fenster die neue Größe meldet.
+			self.size = match self.content
+			{
+				ScreenContent::Grid(_) => ScreenSize::from_grid(width, height, self.size.font_size),
+				ScreenContent::Canvas(_) => ScreenSize::from_pixels(width, height, self.size.font_size),
+			}.with_surface(self.size.surface_width, self.size.surface_height);
 			self.window.lock().unwrap().as_ref().unwrap().set_inner_size(PhysicalSize{width: self.size.window_width as f32, height: self.size.window_height as f32});
 			self.renderer.resize(self.size);
-			self.input.resize(buffer.width, buffer.height);
+			self.input.set_mode(InputMode::new(&self.content, &self.size));
+			self.input.set_viewport(self.size.viewport());
 		}
 	}
+
+	/// Passt die Zeichenfläche an die neue Fenstergröße an. Der Inhalt wird skaliert.
+	fn resize_surface(&mut self, width: u32, height: u32)
+	{
+		// Minimierte Fenster haben keine Fläche.
+		if width == 0 || height == 0 { return; }
+		if width == self.size.surface_width && height == self.size.surface_height { return; }
+		self.size = self.size.with_surface(width, height);
+		self.renderer.resize(self.size);
+		self.input.set_viewport(self.size.viewport());
+		self.window.lock().unwrap().as_ref().unwrap().request_redraw();
+	}
 }
 
 impl Component for ScreenComponent
@@ -201,87 +505,155 @@ impl Component for ScreenComponent
     }
 }
 
+/// Art der Maus Koordinaten, abhängig von der gebundenen GPU.
+#[derive(Debug, Clone, Copy)]
+enum InputMode
+{
+	/// GPU T1: Zellen des Zeichen Rasters.
+	Grid(Size),
+	/// GPU T2: Pixel der Zeichenfläche.
+	Pixel,
+}
+
+impl InputMode
+{
+	fn new(content: &ScreenContent, size: &ScreenSize) -> Self
+	{
+		match content
+		{
+			ScreenContent::Grid(_) => InputMode::Grid(size.cell_size()),
+			ScreenContent::Canvas(_) => InputMode::Pixel,
+		}
+	}
+
+	/// Höhe einer Scroll Zeile in Pixeln.
+	fn line_height(&self) -> f64
+	{
+		match self
+		{
+			InputMode::Grid(cell) => cell.height as f64,
+			InputMode::Pixel => PIXELS_PER_LINE,
+		}
+	}
+}
+
+/// Umrechnung von Pixel Scrolling (Touchpad) in Zeilen bei einer GPU T2.
+const PIXELS_PER_LINE: f64 = 20.0;
+
+/// Bits des FIN Modifier Bitfelds.
+const MOD_LEFT_MOUSE: i32 = 1 << 0;
+const MOD_RIGHT_MOUSE: i32 = 1 << 1;
+pub(super) const MOD_CTRL: i32 = 1 << 2;
+pub(super) const MOD_SHIFT: i32 = 1 << 3;
+pub(super) const MOD_ALT: i32 = 1 << 4;
+const MOD_LOGO: i32 = 1 << 5;
+
 struct InputHelper
 {
 	emitter: Option<EventEmitter>,
-	width: u32,
-	height: u32,
-	mouseX: i32,
-	mouseY: i32,
+	mode: InputMode,
+	viewport: Viewport,
+	mouseX: f64,
+	mouseY: f64,
+	buttons: i32,
 	modifiers: i32,
 	close: bool
 }
 
 impl InputHelper
 {
-	pub fn new(emitter: Option<EventEmitter>, width: u32, height: u32) -> Self
+	pub fn new(emitter: Option<EventEmitter>, mode: InputMode, viewport: Viewport) -> Self
 	{
-		Self{emitter, width, height, mouseX: 0, mouseY: 0, modifiers: 0, close: false }
+		Self{emitter, mode, viewport, mouseX: 0.0, mouseY: 0.0, buttons: 0, modifiers: 0, close: false }
 	}
 
 	pub fn update<T>(&mut self, event: &event::Event<T>)
 	{
 		if self.emitter.is_some()
 		{
-			match &event
+			if let event::Event::WindowEvent { event, .. } = &event
 			{
-				event::Event::WindowEvent { event, .. } => self.handleWindowEvents(event),
-				_ => {}
+				self.handleWindowEvents(event);
 			}
 		}
 	}
 
-	pub fn resize(&mut self, width: u32, height: u32)
+	pub fn set_mode(&mut self, mode: InputMode)
 	{
-		self.width = width;
-		self.height = height;
+		self.mode = mode;
+	}
+
+	pub fn set_viewport(&mut self, viewport: Viewport)
+	{
+		self.viewport = viewport;
 	}
 
 	fn handleWindowEvents(&mut self, event: &WindowEvent)
 	{
-		let em = self.emitter.as_mut().unwrap();
 		match event {
 			WindowEvent::MouseInput {
-				state: ElementState::Pressed,
-				button,
-				..
-			} => {
-				em.send(Signal::numArgs(
-					EVENT_MOUSE_DOWN,
-					em.owner(), vec![
-					self.mouseX,
-					self.mouseY,
-					mouse_button_to_int(button)
-				]));
-			},
-			WindowEvent::MouseInput {
-				state: ElementState::Released,
+				state,
 				button,
 				..
 			} => {
-				em.send(Signal::numArgs(
-					EVENT_MOUSE_UP,
-					em.owner(), vec![
-					self.mouseX,
-					self.mouseY,
-					mouse_button_to_int(button)
-				]));
+				let (eventType, bit) = match state
+				{
+					ElementState::Pressed => (EVENT_MOUSE_DOWN, mouse_button_to_bit(button)),
+					ElementState::Released => (EVENT_MOUSE_UP, mouse_button_to_bit(button)),
+				};
+				// Beim Loslassen enthält das Bitfeld noch die losgelassene Taste.
+				self.buttons |= bit;
+				let signal = match self.mode
+				{
+					InputMode::Grid(_) => self.grid_signal(eventType),
+					InputMode::Pixel => self.position_signal(eventType, vec![]),
+				};
+				self.send(signal);
+				if *state == ElementState::Released
+				{
+					self.buttons &= !bit;
+				}
 			},
 			WindowEvent::CursorMoved {
 				position,
 				..
 			} => {
-				let mouseX = (position.x / self.width as f64) as i32;
-				let mouseY = (position.y / self.height as f64) as i32;
+				// Bewegungen im Rand des skalierten Fensters werden ignoriert.
+				let (x, y) = match self.viewport.map_position(position.x, position.y)
+				{
+					Some(p) => p,
+					None => return
+				};
+				let (mouseX, mouseY) = match self.mode
+				{
+					InputMode::Grid(cell) => (
+						(x / cell.width as f64).floor(),
+						(y / cell.height as f64).floor()
+					),
+					InputMode::Pixel => (x, y),
+				};
 				if mouseX == self.mouseX && mouseY == self.mouseY {return;}
 				self.mouseX = mouseX;
 				self.mouseY = mouseY;
-				em.send(Signal::numArgs(
-					EVENT_MOUSE_MOVE,
-					em.owner(), vec![
-					self.mouseX,
-					self.mouseY
-				]));
+				let signal = match self.mode
+				{
+					InputMode::Grid(_) => self.grid_signal(EVENT_MOUSE_MOVE),
+					InputMode::Pixel => self.position_signal(EVENT_MOUSE_MOVE, vec![]),
+				};
+				self.send(signal);
+			},
+			WindowEvent::MouseWheel {
+				delta,
+				..
+			} => {
+				// Mausräder melden Zeilen, Touchpads Pixel. Beides wird in Zeilen umgerechnet.
+				let delta = match delta
+				{
+					MouseScrollDelta::LineDelta(_, y) => *y as f64,
+					MouseScrollDelta::PixelDelta(p) => p.y / self.viewport.scale as f64 / self.mode.line_height(),
+				};
+				let signal = self.position_signal(EVENT_MOUSE_WHEEL, vec![delta]);
+				self.send(signal);
 			},
 			WindowEvent::KeyboardInput {
 				input,
@@ -292,47 +664,175 @@ impl InputHelper
         			ElementState::Pressed => EVENT_KEY_DOWN,
         			ElementState::Released => EVENT_KEY_UP,
     			};
-				let key = match input.virtual_keycode
+				let (ch, code) = match input.virtual_keycode
 				{
-					Some(k) => k as i32,
-					None => 0
+					Some(k) => (key_char(k), key_code(k)),
+					None => (0, 0)
 				};
-				em.send(Signal::numArgs(
+				let signal = Signal::numArgs(
 					eventType,
-					em.owner(), vec![
-					input.scancode as i32,
-					key, self.modifiers
-				]));
+					self.owner(), vec![
+					ch, code, self.modifier_bits()
+				]);
+				self.send(signal);
+			},
+			WindowEvent::ReceivedCharacter(ch) => {
+				// Steuerzeichen wie Backspace oder Enter kommen nur über OnKeyDown.
+				// Mit IME zusammengesetzte Texte werden Zeichen für Zeichen gemeldet.
+				if ch.is_control() {return;}
+				let signal = Signal::new(
+					EVENT_KEY_CHAR,
+					self.owner(), vec![
+					Value::from(*ch),
+					Value::from(self.modifier_bits())
+				]);
+				self.send(signal);
 			},
 			WindowEvent::ModifiersChanged(modifiers) => {
 				let mut bits = 0i32;
-				//if mouseleft() { bits |=  1 << 0; }
-				//if mouseright() { bits |=  1 << 1; }
-				if modifiers.ctrl()  { bits |= 1 << 2; }
-				if modifiers.shift() { bits |= 1 << 3; }
-				if modifiers.alt()   { bits |= 1 << 4; }
-				if modifiers.logo()  { bits |= 1 << 5; }
+				if modifiers.ctrl()  { bits |= MOD_CTRL; }
+				if modifiers.shift() { bits |= MOD_SHIFT; }
+				if modifiers.alt()   { bits |= MOD_ALT; }
+				if modifiers.logo()  { bits |= MOD_LOGO; }
 				self.modifiers = bits;
 			},
 			WindowEvent::CloseRequested => {
 				self.close = true;
-				em.send(Signal::noArgs(EVENT_WINDOW_CLOSED, em.owner()));
+				let signal = Signal::noArgs(EVENT_WINDOW_CLOSED, self.owner());
+				self.send(signal);
 			},
 			_ => {}
 		}
 	}
 
+	/// FIN Modifier Bitfeld aus gedrückten Maustasten und Modifier Tasten.
+	#[inline]
+	fn modifier_bits(&self) -> i32
+	{
+		self.buttons | self.modifiers
+	}
+
+	/// Signal einer GPU T1: Zelle und das FIN Modifier Bitfeld.
+	fn grid_signal(&self, eventType: &'static str) -> Signal
+	{
+		Signal::numArgs(eventType, self.owner(), vec![
+			self.mouseX as i32,
+			self.mouseY as i32,
+			self.modifier_bits()
+		])
+	}
+
+	/// Signal mit Position (Zelle bei T1, Pixel bei T2), weiteren Argumenten und dem FIN Modifier Bitfeld.
+	fn position_signal(&self, eventType: &'static str, args: Vec<f64>) -> Signal
+	{
+		let mut params = vec![Value::from(self.mouseX), Value::from(self.mouseY)];
+		params.extend(args.into_iter().map(Value::from));
+		params.push(Value::from(self.modifier_bits()));
+		Signal::new(eventType, self.owner(), params)
+	}
+
+	#[inline]
+	fn owner(&self) -> UID
+	{
+		self.emitter.as_ref().unwrap().owner()
+	}
+
+	#[inline]
+	fn send(&mut self, signal: Signal)
+	{
+		self.emitter.as_mut().unwrap().send(signal);
+	}
+
 	fn closeRequested(&self) -> bool
 	{
 		self.close
 	}
 }
 
-fn mouse_button_to_int(button: &MouseButton) -> i32 {
-    match button {
-        MouseButton::Left => 0,
-        MouseButton::Right => 1,
-        MouseButton::Middle => 2,
-        MouseButton::Other(byte) => *byte as i32,
-    }
-}
\ No newline at end of file
+/// Fenster laufen in einem eigenen Thread. Das erlaubt winit nur auf diesen Plattformen.
+/// Unter macOS müsste die Event Loop den Main Thread von Lua blockieren.
+const WINDOW_THREAD_SUPPORTED: bool = cfg!(any(
+	target_os = "windows",
+	target_os = "linux",
+	target_os = "dragonfly",
+	target_os = "freebsd",
+	target_os = "netbsd",
+	target_os = "openbsd"
+));
+
+/// Erzeugt die Event Loop für den Fenster Thread.
+/// Unter Linux/BSD wählt winit über WINIT_UNIX_BACKEND zwischen X11 und Wayland.
+#[cfg(target_os = "windows")]
+fn create_event_loop() -> EventLoop<()>
+{
+	use winit::platform::windows::EventLoopExtWindows;
+	EventLoop::new_any_thread()
+}
+
+#[cfg(any(
+	target_os = "linux",
+	target_os = "dragonfly",
+	target_os = "freebsd",
+	target_os = "netbsd",
+	target_os = "openbsd"
+))]
+fn create_event_loop() -> EventLoop<()>
+{
+	use winit::platform::unix::EventLoopExtUnix;
+	EventLoop::new_any_thread()
+}
+
+/// Ob winit eine Verbindung zum Display aufbauen kann. Ohne Display bricht create_event_loop ab.
+#[cfg(target_os = "windows")]
+fn display_available() -> bool
+{
+	true
+}
+
+#[cfg(any(
+	target_os = "linux",
+	target_os = "dragonfly",
+	target_os = "freebsd",
+	target_os = "netbsd",
+	target_os = "openbsd"
+))]
+fn display_available() -> bool
+{
+	std::env::var_os("WAYLAND_DISPLAY").is_some() || std::env::var_os("DISPLAY").is_some()
+}
+
+#[cfg(not(any(
+	target_os = "windows",
+	target_os = "linux",
+	target_os = "dragonfly",
+	target_os = "freebsd",
+	target_os = "netbsd",
+	target_os = "openbsd"
+)))]
+fn display_available() -> bool
+{
+	false
+}
+
+/// macOS kennt keine Event Loop außerhalb des Main Threads, open öffnet dort keine Fenster.
+#[cfg(not(any(
+	target_os = "windows",
+	target_os = "linux",
+	target_os = "dragonfly",
+	target_os = "freebsd",
+	target_os = "netbsd",
+	target_os = "openbsd"
+)))]
+fn create_event_loop() -> EventLoop<()>
+{
+	unreachable!("windows are not supported on this platform")
+}
+
+/// Maustasten im FIN Modifier Bitfeld. Weitere Tasten haben kein Bit.
+fn mouse_button_to_bit(button: &MouseButton) -> i32 {
+	match button {
+		MouseButton::Left => MOD_LEFT_MOUSE,
+		MouseButton::Right => MOD_RIGHT_MOUSE,
+		_ => 0,
+	}
+}
@@ -0,0 +1,478 @@
+use std::num::NonZeroU64;
+use std::ops::Range;
+
+use futures::task::SpawnExt;
+use ultraviolet::Mat4;
+use wgpu::{Device, RenderPipeline};
+use wgpu::util::DeviceExt;
+use wgpu_glyph::{GlyphBrush, GlyphBrushBuilder, Layout, Region, Section, Text};
+
+use super::{Color, ScreenSize};
+use super::canvas::{BoxSettings, ClipRect, DrawCall, Frame, Transform2D, Vector2, monospace_advance, monospace_columns};
+use super::text::default_font;
+
+/// Anzahl der Liniensegmente für Kurven.
+const CURVE_SEGMENTS: usize = 24;
+/// Anzahl der Liniensegmente pro abgerundeter Ecke.
+const CORNER_SEGMENTS: usize = 8;
+
+/// Position (x, y) und Farbe (r, g, b, a) eines Vertex.
+type Vertex = [f32; 6];
+
+/// Zeichnet die Befehle einer GPU T2 als Dreiecke.
+/// Texte werden über einen eigenen Glyph Brush gezeichnet, die Reihenfolge der Befehle bleibt erhalten.
+pub struct ShapeRenderer
+{
+	size: ScreenSize,
+	pipeline: RenderPipeline,
+	uniform_buffer: wgpu::Buffer,
+	bind_group: wgpu::BindGroup,
+	glyph_brush: GlyphBrush<()>,
+	staging_belt: wgpu::util::StagingBelt,
+	local_pool: futures::executor::LocalPool,
+	local_spawner: futures::executor::LocalSpawner
+}
+
+impl ShapeRenderer
+{
+	pub fn new(device: &Device, size: ScreenSize, texture_format: wgpu::TextureFormat) -> Self
+	{
+		let shader = device.create_shader_module(&wgpu::include_wgsl!("../shaders/shapes.wgsl"));
+
+		let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+			label: Some("shapes:renderer_uniform_buffer"),
+			contents: bytemuck::cast_slice(&ShapeRenderer::locals(&size)),
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+		});
+
+		let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some("shapes:renderer_bind_group_layout"),
+			entries: &[
+				wgpu::BindGroupLayoutEntry {
+					binding: 0,
+					visibility: wgpu::ShaderStages::VERTEX,
+					ty: wgpu::BindingType::Buffer {
+						ty: wgpu::BufferBindingType::Uniform,
+						has_dynamic_offset: false,
+						min_binding_size: NonZeroU64::new(32),
+					},
+					count: None,
+				},
+			],
+		});
+		let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("shapes:renderer_bind_group"),
+			layout: &bind_group_layout,
+			entries: &[
+				wgpu::BindGroupEntry {
+					binding: 0,
+					resource: uniform_buffer.as_entire_binding(),
+				},
+			],
+		});
+
+		let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+			label: Some("shapes:renderer_pipeline_layout"),
+			bind_group_layouts: &[&bind_group_layout],
+			push_constant_ranges: &[],
+		});
+		let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+			label: Some("shapes:renderer_pipeline"),
+			layout: Some(&pipeline_layout),
+			vertex: wgpu::VertexState {
+				module: &shader,
+				entry_point: "vs_main",
+				buffers: &[wgpu::VertexBufferLayout {
+					array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+					step_mode: wgpu::VertexStepMode::Vertex,
+					attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x4],
+				}],
+			},
+			primitive: wgpu::PrimitiveState::default(),
+			depth_stencil: None,
+			multisample: wgpu::MultisampleState::default(),
+			fragment: Some(wgpu::FragmentState {
+				module: &shader,
+				entry_point: "fs_main",
+				targets: &[wgpu::ColorTargetState {
+					format: texture_format,
+					blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+					write_mask: wgpu::ColorWrites::ALL,
+				}],
+			}),
+			multiview: None,
+		});
+
+		let glyph_brush = GlyphBrushBuilder::using_font(default_font()).build(device, texture_format);
+		let staging_belt = wgpu::util::StagingBelt::new(1024);
+		let local_pool = futures::executor::LocalPool::new();
+		let local_spawner = local_pool.spawner();
+
+		Self
+		{
+			size,
+			pipeline,
+			uniform_buffer,
+			bind_group,
+			glyph_brush,
+			staging_belt,
+			local_pool,
+			local_spawner,
+		}
+	}
+
+	pub fn draw(&mut self, device: &Device, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView, frame: &Frame)
+	{
+		let mut tess = Tessellator::default();
+		for (i, cmd) in frame.commands.iter().enumerate()
+		{
+			tess.tessellate(i, &cmd.call, &cmd.transform, cmd.clip);
+		}
+
+		let vertex_buffer = match tess.vertices.is_empty()
+		{
+			true => None,
+			false => Some(device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+				label: Some("shapes:renderer_vertex_buffer"),
+				contents: bytemuck::cast_slice(&tess.vertices),
+				usage: wgpu::BufferUsages::VERTEX,
+			}))
+		};
+
+		for segment in &tess.segments
+		{
+			match segment
+			{
+				Segment::Shapes { range, clip } => {
+					let scissor = match self.scissor(clip)
+					{
+						Some(s) => s,
+						None => continue
+					};
+					let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+						label: Some("shapes::pipeline render pass"),
+						color_attachments: &[wgpu::RenderPassColorAttachment {
+							view: target,
+							resolve_target: None,
+							ops: wgpu::Operations {
+								load: wgpu::LoadOp::Load,
+								store: true,
+							},
+						}],
+						depth_stencil_attachment: None,
+					});
+					rpass.set_pipeline(&self.pipeline);
+					rpass.set_bind_group(0, &self.bind_group, &[]);
+					rpass.set_vertex_buffer(0, vertex_buffer.as_ref().unwrap().slice(..));
+					rpass.set_scissor_rect(scissor.x, scissor.y, scissor.width, scissor.height);
+					rpass.draw(range.clone(), 0..1);
+				},
+				Segment::Text(index) => {
+					let cmd = &frame.commands[*index];
+					if let DrawCall::Text { position, text, size, color, monospace } = &cmd.call
+					{
+						let scissor = match self.scissor(&cmd.clip)
+						{
+							Some(s) => s,
+							None => continue
+						};
+						// Monospace Texte stehen wie bei measureText in festen Spalten.
+						let sections = match monospace
+						{
+							true => {
+								let advance = monospace_advance(*size);
+								monospace_columns(text).into_iter()
+									.map(|(column, cluster)| (position.x + column as f32 * advance, cluster))
+									.collect()
+							},
+							false => vec![(position.x, text.as_str())],
+						};
+						for (x, text) in sections
+						{
+							self.glyph_brush.queue(Section {
+								screen_position: (x, position.y),
+								bounds: (f32::INFINITY, f32::INFINITY),
+								text: vec![Text::new(text).with_color(*color).with_scale(*size)],
+								layout: Layout::default_single_line()
+							});
+						}
+						let projection = Mat4::from(wgpu_glyph::orthographic_projection(self.size.surface_width, self.size.surface_height));
+						let transform = projection * Mat4::from(self.viewport_transform().then(&cmd.transform).matrix());
+						self.glyph_brush
+							.draw_queued_with_transform_and_scissoring(
+								device,
+								&mut self.staging_belt,
+								encoder,
+								target,
+								*transform.as_array(),
+								scissor,
+							)
+							.expect("Draw queued");
+					}
+				},
+			}
+		}
+		self.staging_belt.finish();
+	}
+
+	pub fn clean_frame(&mut self)
+	{
+		self.local_spawner.spawn(self.staging_belt.recall()).expect("Recall staging belt");
+		self.local_pool.run_until_stalled();
+	}
+
+	pub fn resize(&mut self, size: ScreenSize, queue: &wgpu::Queue)
+	{
+		self.size = size;
+		queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&ShapeRenderer::locals(&size)));
+	}
+
+	#[inline]
+	fn locals(size: &ScreenSize) -> [f32; 8]
+	{
+		let viewport = size.viewport();
+		[size.surface_width as f32, size.surface_height as f32, viewport.x, viewport.y, viewport.scale, viewport.scale, 0.0, 0.0]
+	}
+
+	/// Abbildung der Zeichenfläche der GPU in den Viewport des Fensters.
+	#[inline]
+	fn viewport_transform(&self) -> Transform2D
+	{
+		viewport_transform(&self.size)
+	}
+
+	#[inline]
+	fn scissor(&self, clip: &Option<ClipRect>) -> Option<Region>
+	{
+		scissor_region(&self.size, clip)
+	}
+}
+
+#[inline]
+fn viewport_transform(size: &ScreenSize) -> Transform2D
+{
+	let viewport = size.viewport();
+	Transform2D::translate(Vector2::new(viewport.x, viewport.y))
+		.then(&Transform2D::scale(Vector2::new(viewport.scale, viewport.scale)))
+}
+
+/// Begrenzt den Clip Bereich auf das Ziel. Leere Bereiche werden nicht gezeichnet.
+fn scissor_region(size: &ScreenSize, clip: &Option<ClipRect>) -> Option<Region>
+{
+	let screen = ClipRect{x: 0.0, y: 0.0, width: size.window_width as f32, height: size.window_height as f32};
+	let rect = match clip
+	{
+		Some(c) => c.intersect(&screen),
+		None => screen
+	};
+	let v = viewport_transform(size);
+	let surface = ClipRect{x: 0.0, y: 0.0, width: size.surface_width as f32, height: size.surface_height as f32};
+	let rect = ClipRect::bounding(&[
+		v.apply(Vector2::new(rect.x, rect.y)),
+		v.apply(Vector2::new(rect.x + rect.width, rect.y + rect.height))
+	]).intersect(&surface);
+	// Außerhalb der Fläche liegt x bzw. y hinter dem Rand.
+	if rect.width <= 0.0 || rect.height <= 0.0 { return None; }
+	let x = rect.x.floor() as u32;
+	let y = rect.y.floor() as u32;
+	let width = ((rect.x + rect.width).ceil() as u32).min(size.surface_width).saturating_sub(x);
+	let height = ((rect.y + rect.height).ceil() as u32).min(size.surface_height).saturating_sub(y);
+	if width == 0 || height == 0 { return None; }
+	Some(Region{x, y, width, height})
+}
+
+enum Segment
+{
+	Shapes { range: Range<u32>, clip: Option<ClipRect> },
+	Text(usize),
+}
+
+/// Zerlegt Zeichenbefehle in Dreiecke in Bildschirmkoordinaten.
+#[derive(Default)]
+struct Tessellator
+{
+	vertices: Vec<Vertex>,
+	segments: Vec<Segment>,
+}
+
+impl Tessellator
+{
+	fn tessellate(&mut self, index: usize, call: &DrawCall, t: &Transform2D, clip: Option<ClipRect>)
+	{
+		let start = self.vertices.len() as u32;
+		match call
+		{
+			DrawCall::Rect { position, size, color, rotation } => {
+				let local = t.then(&Transform2D::translate(*position)).then(&Transform2D::rotate(*rotation));
+				self.polygon(&local, &rect_points(*size), *color);
+			},
+			DrawCall::Line { points, thickness, color } => {
+				self.polyline(t, points, *thickness, *color, false);
+			},
+			DrawCall::Box(settings) => self.draw_box(t, settings),
+			DrawCall::Text { .. } => {
+				self.segments.push(Segment::Text(index));
+				return;
+			},
+			DrawCall::Spline { start, start_direction, end, end_direction, thickness, color } => {
+				// Hermite Spline als kubische Bezier Kurve
+				let p1 = start.add(start_direction.mul(1.0 / 3.0));
+				let p2 = end.sub(end_direction.mul(1.0 / 3.0));
+				self.polyline(t, &bezier_points(*start, p1, p2, *end), *thickness, *color, false);
+			},
+			DrawCall::Bezier { p0, p1, p2, p3, thickness, color } => {
+				self.polyline(t, &bezier_points(*p0, *p1, *p2, *p3), *thickness, *color, false);
+			},
+		}
+		let end = self.vertices.len() as u32;
+		if start == end { return; }
+
+		// Aufeinanderfolgende Formen mit gleichem Clip Bereich zusammenfassen
+		if let Some(Segment::Shapes { range, clip: last }) = self.segments.last_mut()
+		{
+			if *last == clip && range.end == start
+			{
+				range.end = end;
+				return;
+			}
+		}
+		self.segments.push(Segment::Shapes { range: start..end, clip });
+	}
+
+	fn draw_box(&mut self, t: &Transform2D, b: &BoxSettings)
+	{
+		let mut local = t.then(&Transform2D::translate(b.position)).then(&Transform2D::rotate(b.rotation));
+		if b.has_centered_origin
+		{
+			local = local.then(&Transform2D::translate(b.size.mul(-0.5)));
+		}
+
+		let points = match b.is_rounded
+		{
+			true => rounded_rect_points(b.size, [b.radii.x, b.radii.y, b.radii.z, b.radii.w]),
+			false => rect_points(b.size)
+		};
+		if b.color.a > 0.0
+		{
+			self.polygon(&local, &points, b.color);
+		}
+		if b.has_outline && b.outline_thickness > 0.0
+		{
+			self.polyline(&local, &points, b.outline_thickness, b.outline_color, true);
+		}
+	}
+
+	#[inline]
+	fn vertex(&mut self, p: Vector2, color: Color)
+	{
+		self.vertices.push([p.x, p.y, color.r, color.g, color.b, color.a]);
+	}
+
+	/// Konvexes Polygon als Dreiecksfächer.
+	fn polygon(&mut self, t: &Transform2D, points: &[Vector2], color: Color)
+	{
+		if points.len() < 3 { return; }
+		let first = t.apply(points[0]);
+		for i in 1..(points.len() - 1)
+		{
+			self.vertex(first, color);
+			self.vertex(t.apply(points[i]), color);
+			self.vertex(t.apply(points[i + 1]), color);
+		}
+	}
+
+	/// Linienzug mit Stärke. Die Breite wird im lokalen Raum berechnet und mit transformiert.
+	fn polyline(&mut self, t: &Transform2D, points: &[Vector2], thickness: f32, color: Color, closed: bool)
+	{
+		if points.len() < 2 { return; }
+		let count = if closed { points.len() } else { points.len() - 1 };
+		let half = thickness * 0.5;
+		for i in 0..count
+		{
+			let a = points[i];
+			let b = points[(i + 1) % points.len()];
+			let dir = b.sub(a);
+			let len = dir.length();
+			if len <= f32::EPSILON { continue; }
+			let normal = Vector2::new(-dir.y / len * half, dir.x / len * half);
+			let quad = [a.add(normal), b.add(normal), b.sub(normal), a.sub(normal)];
+			self.polygon(t, &quad, color);
+		}
+	}
+}
+
+fn rect_points(size: Vector2) -> Vec<Vector2>
+{
+	vec![Vector2::ZERO, Vector2::new(size.x, 0.0), size, Vector2::new(0.0, size.y)]
+}
+
+/// Eckradien in der Reihenfolge oben links, oben rechts, unten rechts, unten links.
+fn rounded_rect_points(size: Vector2, radii: [f32; 4]) -> Vec<Vector2>
+{
+	let max = size.x.min(size.y) * 0.5;
+	let centers = [
+		|r: f32, _: Vector2| Vector2::new(r, r),
+		|r: f32, s: Vector2| Vector2::new(s.x - r, r),
+		|r: f32, s: Vector2| Vector2::new(s.x - r, s.y - r),
+		|r: f32, s: Vector2| Vector2::new(r, s.y - r),
+	];
+	let mut points = Vec::with_capacity(4 * (CORNER_SEGMENTS + 1));
+	for (corner, center) in centers.iter().enumerate()
+	{
+		let r = radii[corner].clamp(0.0, max);
+		let c = center(r, size);
+		// Startwinkel der Ecke, im Uhrzeigersinn beginnend oben links
+		let start = (180.0 + 90.0 * corner as f32).to_radians();
+		for i in 0..=CORNER_SEGMENTS
+		{
+			let angle = start + std::f32::consts::FRAC_PI_2 * i as f32 / CORNER_SEGMENTS as f32;
+			points.push(Vector2::new(c.x + angle.cos() * r, c.y + angle.sin() * r));
+		}
+	}
+	points
+}
+
+fn bezier_points(p0: Vector2, p1: Vector2, p2: Vector2, p3: Vector2) -> Vec<Vector2>
+{
+	(0..=CURVE_SEGMENTS).map(|i| {
+		let t = i as f32 / CURVE_SEGMENTS as f32;
+		let u = 1.0 - t;
+		p0.mul(u * u * u)
+			.add(p1.mul(3.0 * u * u * t))
+			.add(p2.mul(3.0 * u * t * t))
+			.add(p3.mul(t * t * t))
+	}).collect()
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use super::super::canvas::CanvasHandle;
+
+	fn clip_of(setup: impl FnOnce(&mut CanvasHandle)) -> Option<ClipRect>
+	{
+		let mut canvas = CanvasHandle::new(100, 50);
+		setup(&mut canvas);
+		canvas.draw(DrawCall::Rect{ position: Vector2::new(0.0, 0.0), size: Vector2::new(10.0, 10.0), color: Color::new(1.0, 1.0, 1.0, 1.0), rotation: 0.0 });
+		canvas.flush();
+		let frame = canvas.frame.lock().unwrap();
+		frame.commands[0].clip
+	}
+
+	#[test]
+	fn offscreen_clips_are_skipped()
+	{
+		let size = ScreenSize::from_pixels(100, 50, 16);
+		let right = clip_of(|c| c.push_clip_rect(Vector2::new(150.0, 10.0), Vector2::new(20.0, 20.0)));
+		assert!(scissor_region(&size, &right).is_none());
+		let below = clip_of(|c| c.push_clip_rect(Vector2::new(10.0, 80.0), Vector2::new(20.0, 20.0)));
+		assert!(scissor_region(&size, &below).is_none());
+
+		let partial = clip_of(|c| c.push_clip_rect(Vector2::new(90.0, 40.0), Vector2::new(20.0, 20.0)));
+		let region = scissor_region(&size, &partial).unwrap();
+		assert_eq!((region.x, region.y, region.width, region.height), (90, 40, 10, 10));
+		let full = scissor_region(&size, &None).unwrap();
+		assert_eq!((full.width, full.height), (100, 50));
+	}
+}
@@ -0,0 +1,366 @@
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{Buffer, Color};
+use super::headless::{Image, render_software};
+
+const SNAPSHOT_HEADER: &str = "FreenSnapshot 2";
+/// Version 1 trennte Zellen nicht und wird über Graphem Cluster geteilt.
+const V1_SNAPSHOT_HEADER: &str = "FreenSnapshot 1";
+/// Trennt die Zellen einer Textzeile. Steuerzeichen kommen in Zellen nicht vor.
+const CELL_SEPARATOR: char = '\t';
+/// Obergrenze für die Zellen eines geladenen Snapshots.
+const MAX_SNAPSHOT_CELLS: usize = 1 << 24;
+/// Maximale Anzahl an Zellen, die in einem Diff einzeln aufgeführt werden.
+const MAX_REPORTED_CELLS: usize = 32;
+
+/// Speichert Zeichen und Farben eines Buffers als lesbaren Text Snapshot.
+///
+/// Format: Kopfzeile, Größe, je eine Zeile mit den durch Tabs getrennten Zellen,
+/// gefolgt von den Vordergrund- und Hintergrundfarben als RRGGBBAA.
+/// Durch die Trennung bleiben auch einzelne kombinierende Zeichen eigene Zellen.
+pub fn save_snapshot(buffer: &Buffer, path: &Path) -> Result<(), Box<dyn Error>>
+{
+	let mut out = String::new();
+	out.push_str(SNAPSHOT_HEADER);
+	out.push('\n');
+	out.push_str(&format!("{} {}\n", buffer.width, buffer.height));
+	for y in 0..buffer.height
+	{
+		let line: Vec<String> = row(buffer, y).map(|i| cell_text(buffer, i)).collect();
+		out.push_str(&line.join(&CELL_SEPARATOR.to_string()));
+		out.push('\n');
+	}
+	for (name, colors) in [("fg", &buffer.foreground), ("bg", &buffer.background)]
+	{
+		out.push_str(name);
+		out.push('\n');
+		for y in 0..buffer.height
+		{
+			let line: Vec<String> = row(buffer, y).map(|i| hex(&colors[i])).collect();
+			out.push_str(&line.join(" "));
+			out.push('\n');
+		}
+	}
+	fs::write(path, out)?;
+	Ok(())
+}
+
+/// Eingelesener Text Snapshot. Farben bleiben als Bytes erhalten, damit der Vergleich verlustfrei ist.
+struct Snapshot
+{
+	width: u32,
+	height: u32,
+	cells: Vec<String>,
+	foreground: Vec<[u8; 4]>,
+	background: Vec<[u8; 4]>,
+}
+
+fn load_snapshot(path: &Path) -> Result<Snapshot, Box<dyn Error>>
+{
+	let content = fs::read_to_string(path)?;
+	let mut lines = content.lines();
+	let separated = match lines.next()
+	{
+		Some(SNAPSHOT_HEADER) => true,
+		Some(V1_SNAPSHOT_HEADER) => false,
+		_ => return Err("Invalid snapshot header".into())
+	};
+
+	let mut dims = lines.next().ok_or("Missing snapshot size")?.split(' ');
+	let width: u32 = dims.next().ok_or("Missing width")?.parse()?;
+	let height: u32 = dims.next().ok_or("Missing height")?.parse()?;
+	// Die Größe stammt aus der Datei und wird vor dem Reservieren geprüft.
+	let size = (width as usize).checked_mul(height as usize)
+		.filter(|size| *size <= MAX_SNAPSHOT_CELLS)
+		.ok_or("Snapshot size too large")?;
+	// Textzeilen, fg, Farbzeilen, bg, Farbzeilen
+	if lines.clone().count() != height as usize * 3 + 2 { return Err("Snapshot lines do not match its size".into()); }
+	let mut snapshot = Snapshot{
+		width,
+		height,
+		cells: Vec::with_capacity(size),
+		foreground: Vec::with_capacity(size),
+		background: Vec::with_capacity(size),
+	};
+
+	for y in 0..height
+	{
+		let line = lines.next().ok_or("Missing text line")?;
+		let cells: Vec<String> = match separated
+		{
+			true if width == 0 => Vec::new(),
+			true => line.split(CELL_SEPARATOR).map(String::from).collect(),
+			false => line.graphemes(true).map(String::from).collect(),
+		};
+		if cells.len() != width as usize { return Err(format!("Invalid text line {}", y).into()); }
+		snapshot.cells.extend(cells);
+	}
+	for name in ["fg", "bg"]
+	{
+		if lines.next() != Some(name) { return Err(format!("Missing {} section", name).into()); }
+		let colors = match name
+		{
+			"fg" => &mut snapshot.foreground,
+			_ => &mut snapshot.background,
+		};
+		for y in 0..height
+		{
+			let line = lines.next().ok_or("Missing color line")?;
+			let row = line.split(' ').map(parse_hex).collect::<Result<Vec<[u8; 4]>, _>>()?;
+			if row.len() != width as usize { return Err(format!("Invalid {} line {}", name, y).into()); }
+			colors.extend(row);
+		}
+	}
+	Ok(snapshot)
+}
+
+/// Vergleicht einen Buffer mit einem gespeicherten Text Snapshot.
+/// Gibt eine Liste lesbarer Abweichungen zurück, die bei Gleichheit leer ist.
+pub fn compare_snapshot(buffer: &Buffer, path: &Path) -> Vec<String>
+{
+	let expected = match load_snapshot(path)
+	{
+		Ok(b) => b,
+		Err(e) => return vec![format!("Could not load snapshot {}: {}", path.display(), e)]
+	};
+	if expected.width != buffer.width || expected.height != buffer.height
+	{
+		return vec![format!("Buffer size differs: expected {}x{}, got {}x{}",
+			expected.width, expected.height, buffer.width, buffer.height)];
+	}
+
+	let mut report = DiffReport::new();
+	for i in 0..buffer.chars.len()
+	{
+		let mut parts = Vec::new();
+		let (exp_ch, act_ch) = (&expected.cells[i], cell_text(buffer, i));
+		if *exp_ch != act_ch
+		{
+			parts.push(format!("char '{}' != '{}'", exp_ch, act_ch));
+		}
+		if expected.foreground[i] != buffer.foreground[i].bytes()
+		{
+			parts.push(format!("fg {} != {}", hex_bytes(expected.foreground[i]), hex(&buffer.foreground[i])));
+		}
+		if expected.background[i] != buffer.background[i].bytes()
+		{
+			parts.push(format!("bg {} != {}", hex_bytes(expected.background[i]), hex(&buffer.background[i])));
+		}
+		if !parts.is_empty()
+		{
+			report.cell(i as u32 % buffer.width, i as u32 / buffer.width, parts.join(", "));
+		}
+	}
+	report.finish()
+}
+
+/// Speichert ein Referenzbild des Buffers.
+/// Es wird immer der CPU Rasterizer verwendet, damit Referenzen auf allen Systemen gleich sind.
+pub fn save_reference_image(buffer: &Buffer, path: &Path, font_size: u32) -> Result<(), Box<dyn Error>>
+{
+	render_software(buffer, font_size)?.save_png(path)
+}
+
+/// Vergleicht den gerenderten Buffer mit einem Referenzbild.
+/// Zellen, in denen ein Farbkanal um mehr als `tolerance` abweicht, werden gemeldet.
+pub fn compare_reference_image(buffer: &Buffer, path: &Path, font_size: u32, tolerance: u8) -> Vec<String>
+{
+	let expected = match load_png(path)
+	{
+		Ok(img) => img,
+		Err(e) => return vec![format!("Could not load image {}: {}", path.display(), e)]
+	};
+	let actual = match render_software(buffer, font_size)
+	{
+		Ok(img) => img,
+		Err(e) => return vec![format!("Could not render image: {}", e)]
+	};
+	if expected.width != actual.width || expected.height != actual.height
+	{
+		return vec![format!("Image size differs: expected {}x{}, got {}x{}",
+			expected.width, expected.height, actual.width, actual.height)];
+	}
+
+	let cell_w = (actual.width / buffer.width).max(1);
+	let cell_h = (actual.height / buffer.height).max(1);
+	let mut report = DiffReport::new();
+	for cy in 0..buffer.height
+	{
+		for cx in 0..buffer.width
+		{
+			let mut delta = 0u8;
+			for y in (cy * cell_h)..((cy + 1) * cell_h)
+			{
+				for x in (cx * cell_w)..((cx + 1) * cell_w)
+				{
+					let (e, a) = (expected.pixel(x, y), actual.pixel(x, y));
+					for c in 0..4
+					{
+						delta = delta.max(e[c].abs_diff(a[c]));
+					}
+				}
+			}
+			if delta > tolerance
+			{
+				let idx = (cx + cy * buffer.width) as usize;
+				report.cell(cx, cy, format!("pixels differ by {} (char '{}')", delta, cell_text(buffer, idx)));
+			}
+		}
+	}
+	report.finish()
+}
+
+fn load_png(path: &Path) -> Result<Image, Box<dyn Error>>
+{
+	let decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+	let mut reader = decoder.read_info()?;
+	let mut data = vec![0; reader.output_buffer_size()];
+	let info = reader.next_frame(&mut data)?;
+	if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight
+	{
+		return Err("Only 8 bit RGBA images are supported".into());
+	}
+	data.truncate(info.buffer_size());
+	Ok(Image{ width: info.width, height: info.height, pixels: data })
+}
+
+/// Sammelt abweichende Zellen und begrenzt die Länge der Ausgabe.
+struct DiffReport
+{
+	lines: Vec<String>,
+	count: usize,
+}
+
+impl DiffReport
+{
+	fn new() -> Self
+	{
+		Self{ lines: Vec::new(), count: 0 }
+	}
+
+	fn cell(&mut self, x: u32, y: u32, msg: String)
+	{
+		self.count += 1;
+		if self.count <= MAX_REPORTED_CELLS
+		{
+			self.lines.push(format!("({}, {}): {}", x, y, msg));
+		}
+	}
+
+	fn finish(mut self) -> Vec<String>
+	{
+		if self.count > MAX_REPORTED_CELLS
+		{
+			self.lines.push(format!("... and {} more cells", self.count - MAX_REPORTED_CELLS));
+		}
+		if self.count > 0
+		{
+			self.lines.insert(0, format!("{} cells differ", self.count));
+		}
+		self.lines
+	}
+}
+
+#[inline]
+fn row(buffer: &Buffer, y: u32) -> std::ops::Range<usize>
+{
+	let start = (y * buffer.width) as usize;
+	start..(start + buffer.width as usize)
+}
+
+/// Text einer Zelle im Snapshot. Leere Zellen, die zweite Spalte breiter Zeichen
+/// und Steuerzeichen werden nicht dargestellt und gelten als Leerzeichen.
+fn cell_text(buffer: &Buffer, idx: usize) -> String
+{
+	let text = buffer.cell_text(idx);
+	match text.chars().next()
+	{
+		Some(ch) if !ch.is_control() => text,
+		_ => " ".to_string()
+	}
+}
+
+#[inline]
+fn hex(col: &Color) -> String
+{
+	hex_bytes(col.bytes())
+}
+
+fn hex_bytes(b: [u8; 4]) -> String
+{
+	format!("{:02X}{:02X}{:02X}{:02X}", b[0], b[1], b[2], b[3])
+}
+
+fn parse_hex(s: &str) -> Result<[u8; 4], Box<dyn Error>>
+{
+	if s.len() != 8 { return Err(format!("Invalid color {}", s).into()); }
+	Ok(u32::from_str_radix(s, 16)?.to_be_bytes())
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	#[test]
+	fn snapshots_keep_clusters()
+	{
+		let white = Color::new(1.0, 1.0, 1.0, 1.0);
+		let black = Color::new(0.0, 0.0, 0.0, 1.0);
+		let mut buffer = Buffer::new(4, 1);
+		buffer.writeText(0, 0, "e\u{301}漢\u{7F}", white, black);
+		let path = std::env::temp_dir().join(format!("freen-snapshot-{}.txt", std::process::id()));
+		save_snapshot(&buffer, &path).unwrap();
+		assert!(compare_snapshot(&buffer, &path).is_empty());
+
+		let mut plain = buffer.clone();
+		plain.writeText(0, 0, "e", white, black);
+		let diff = compare_snapshot(&plain, &path);
+		fs::remove_file(&path).unwrap();
+		assert_eq!(diff, ["1 cells differ", "(0, 0): char 'e\u{301}' != 'e'"]);
+	}
+
+	#[test]
+	fn snapshots_keep_cells_that_would_merge()
+	{
+		let white = Color::new(1.0, 1.0, 1.0, 1.0);
+		let black = Color::new(0.0, 0.0, 0.0, 1.0);
+		let mut buffer = Buffer::new(6, 1);
+		// Kombinierendes Zeichen, ZWJ und zwei Regional Indicators in eigenen Zellen
+		for (x, text) in ["a", "\u{301}", "\u{200D}", "b", "\u{1F1E9}", "\u{1F1EA}"].iter().enumerate()
+		{
+			buffer.writeText(x as i32, 0, text, white, black);
+		}
+		let cells: Vec<String> = (0..6).map(|i| cell_text(&buffer, i)).collect();
+		assert_eq!(cells, ["a", "\u{301}", "\u{200D}", "b", "\u{1F1E9}", "\u{1F1EA}"]);
+		let path = std::env::temp_dir().join(format!("freen-snapshot-merge-{}.txt", std::process::id()));
+		save_snapshot(&buffer, &path).unwrap();
+		let loaded = load_snapshot(&path).unwrap();
+		let diff = compare_snapshot(&buffer, &path);
+		fs::remove_file(&path).unwrap();
+		assert_eq!(loaded.cells, cells);
+		assert!(diff.is_empty(), "{:?}", diff);
+	}
+
+	#[test]
+	fn malformed_snapshots_are_rejected()
+	{
+		let path = std::env::temp_dir().join(format!("freen-snapshot-bad-{}.txt", std::process::id()));
+		let load = |content: &str| {
+			fs::write(&path, content).unwrap();
+			load_snapshot(&path).err().map(|e| e.to_string())
+		};
+		let header = SNAPSHOT_HEADER;
+		assert_eq!(load(&format!("{}\n4294967295 4294967295\n", header)).unwrap(), "Snapshot size too large");
+		assert_eq!(load(&format!("{}\n65536 65536\n", header)).unwrap(), "Snapshot size too large");
+		assert_eq!(load(&format!("{}\n1 1000\na\nfg\n", header)).unwrap(), "Snapshot lines do not match its size");
+		assert_eq!(load(&format!("{}\n2 1\na\nfg\nFFFFFFFF FFFFFFFF\nbg\n000000FF 000000FF\n", header)).unwrap(), "Invalid text line 0");
+		assert!(load(&format!("{}\n2 1\na\tb\nfg\nFFFFFFFF FFFFFFFF\nbg\n000000FF 000000FF\n", header)).is_none());
+		fs::remove_file(&path).unwrap();
+	}
+}
@@ -0,0 +1,297 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use super::{Buffer, Color, WIDE_PLACEHOLDER};
+
+const BUFFER_MAGIC: &str = "FreenBuffer";
+const BUFFER_VERSION: u32 = 2;
+/// Version 1 verwendete DEL als Platzhalter breiter Zeichen.
+const V1_WIDE_PLACEHOLDER: u32 = 0x7F;
+
+/// Binäres Dateiformat eines Buffers.
+/// Farben werden als Gleitkommazahlen gespeichert, damit ein geladener Buffer exakt dem gespeicherten entspricht.
+/// Zahlen sind Little Endian, Strings und Listen beginnen mit ihrer Länge als u64.
+struct BufferFile
+{
+	magic: String,
+	version: u32,
+	width: u32,
+	height: u32,
+	chars: Vec<u32>,
+	/// r, g, b, a je Zelle
+	foreground: Vec<f32>,
+	background: Vec<f32>,
+	/// Zellen mit Graphem Clustern aus mehreren Zeichen
+	cluster_cells: Vec<u32>,
+	cluster_texts: Vec<String>,
+}
+
+/// Speichert Größe, Zeichen und Farben eines Buffers als Binärdatei.
+pub fn save_buffer(buffer: &Buffer, path: &Path) -> Result<(), Box<dyn Error>>
+{
+	let mut clusters: Vec<(&usize, &String)> = buffer.clusters.iter().collect();
+	clusters.sort();
+	let file = BufferFile
+	{
+		magic: BUFFER_MAGIC.to_string(),
+		version: BUFFER_VERSION,
+		width: buffer.width,
+		height: buffer.height,
+		chars: buffer.chars.iter().map(|c| *c as u32).collect(),
+		foreground: buffer.foreground.iter().flat_map(|c| Into::<[f32; 4]>::into(*c)).collect(),
+		background: buffer.background.iter().flat_map(|c| Into::<[f32; 4]>::into(*c)).collect(),
+		cluster_cells: clusters.iter().map(|(idx, _)| **idx as u32).collect(),
+		cluster_texts: clusters.iter().map(|(_, text)| (*text).clone()).collect(),
+	};
+	fs::write(path, file.encode())?;
+	Ok(())
+}
+
+/// Lädt einen mit [`save_buffer`] gespeicherten Buffer.
+pub fn load_buffer(path: &Path) -> Result<Buffer, Box<dyn Error>>
+{
+	let file = BufferFile::decode(&fs::read(path)?)?;
+	if file.magic != BUFFER_MAGIC { return Err("Invalid buffer header".into()); }
+	if file.version != BUFFER_VERSION && file.version != 1 { return Err(format!("Unsupported buffer version {}", file.version).into()); }
+
+	// Die Größe stammt aus der Datei und wird erst nach dem Vergleich mit den Zellen verwendet.
+	let size = (file.width as usize).checked_mul(file.height as usize).ok_or("Buffer size overflows")?;
+	let colors_len = size.checked_mul(4).ok_or("Buffer size overflows")?;
+	if file.chars.len() != size || file.foreground.len() != colors_len || file.background.len() != colors_len
+	{
+		return Err("Buffer data does not match its size".into());
+	}
+	if file.cluster_cells.len() != file.cluster_texts.len()
+	{
+		return Err("Invalid cluster table".into());
+	}
+
+	let mut buffer = Buffer::new(file.width, file.height);
+	for (i, code) in file.chars.iter().enumerate()
+	{
+		let code = match (file.version, *code)
+		{
+			(1, V1_WIDE_PLACEHOLDER) => WIDE_PLACEHOLDER as u32,
+			(_, code) => code,
+		};
+		buffer.chars[i] = char::from_u32(code).ok_or(format!("Invalid char at cell {}", i))?;
+	}
+	buffer.foreground = colors(&file.foreground);
+	buffer.background = colors(&file.background);
+	for (idx, text) in file.cluster_cells.into_iter().zip(file.cluster_texts)
+	{
+		if idx as usize >= size { return Err(format!("Invalid cluster cell {}", idx).into()); }
+		buffer.clusters.insert(idx as usize, text);
+	}
+	Ok(buffer)
+}
+
+#[inline]
+fn colors(values: &[f32]) -> Vec<Color>
+{
+	values.chunks_exact(4).map(|c| Color::new(c[0], c[1], c[2], c[3])).collect()
+}
+
+impl BufferFile
+{
+	fn encode(&self) -> Vec<u8>
+	{
+		let mut out = Vec::new();
+		encode_str(&self.magic, &mut out);
+		for v in [self.version, self.width, self.height]
+		{
+			out.extend(v.to_le_bytes());
+		}
+		encode_list(&self.chars, &mut out, |v, out| out.extend(v.to_le_bytes()));
+		encode_list(&self.foreground, &mut out, |v, out| out.extend(v.to_le_bytes()));
+		encode_list(&self.background, &mut out, |v, out| out.extend(v.to_le_bytes()));
+		encode_list(&self.cluster_cells, &mut out, |v, out| out.extend(v.to_le_bytes()));
+		encode_list(&self.cluster_texts, &mut out, |v, out| encode_str(v, out));
+		out
+	}
+
+	/// Dateien können beschädigt sein. Jede Länge wird gegen die verbleibenden Bytes geprüft,
+	/// bevor Speicher reserviert wird.
+	fn decode(buf: &[u8]) -> Result<Self, Box<dyn Error>>
+	{
+		let mut r = Reader{ buf };
+		let file = BufferFile
+		{
+			magic: r.string()?,
+			version: r.u32()?,
+			width: r.u32()?,
+			height: r.u32()?,
+			chars: r.list(4, Reader::u32)?,
+			foreground: r.list(4, Reader::f32)?,
+			background: r.list(4, Reader::f32)?,
+			cluster_cells: r.list(4, Reader::u32)?,
+			cluster_texts: r.list(8, Reader::string)?,
+		};
+		if !r.buf.is_empty() { return Err("Trailing bytes in buffer file".into()); }
+		Ok(file)
+	}
+}
+
+fn encode_list<T>(items: &[T], out: &mut Vec<u8>, f: fn(&T, &mut Vec<u8>))
+{
+	out.extend((items.len() as u64).to_le_bytes());
+	for item in items
+	{
+		f(item, out);
+	}
+}
+
+#[inline]
+fn encode_str(s: &str, out: &mut Vec<u8>)
+{
+	out.extend((s.len() as u64).to_le_bytes());
+	out.extend(s.as_bytes());
+}
+
+struct Reader<'a>
+{
+	buf: &'a [u8],
+}
+
+impl<'a> Reader<'a>
+{
+	fn bytes(&mut self, len: usize) -> Result<&'a [u8], Box<dyn Error>>
+	{
+		if len > self.buf.len() { return Err("Truncated buffer file".into()); }
+		let (bytes, rest) = self.buf.split_at(len);
+		self.buf = rest;
+		Ok(bytes)
+	}
+
+	fn u32(&mut self) -> Result<u32, Box<dyn Error>>
+	{
+		Ok(u32::from_le_bytes(self.bytes(4)?.try_into()?))
+	}
+
+	fn f32(&mut self) -> Result<f32, Box<dyn Error>>
+	{
+		Ok(f32::from_le_bytes(self.bytes(4)?.try_into()?))
+	}
+
+	/// Anzahl folgender Einträge. Jeder Eintrag belegt mindestens `min_size` Bytes.
+	fn len(&mut self, min_size: usize) -> Result<usize, Box<dyn Error>>
+	{
+		let len = u64::from_le_bytes(self.bytes(8)?.try_into()?);
+		match usize::try_from(len)
+		{
+			Ok(len) if len.saturating_mul(min_size) <= self.buf.len() => Ok(len),
+			_ => Err("Invalid length in buffer file".into())
+		}
+	}
+
+	fn string(&mut self) -> Result<String, Box<dyn Error>>
+	{
+		let len = self.len(1)?;
+		Ok(String::from_utf8(self.bytes(len)?.to_vec())?)
+	}
+
+	fn list<T>(&mut self, min_size: usize, item: fn(&mut Self) -> Result<T, Box<dyn Error>>) -> Result<Vec<T>, Box<dyn Error>>
+	{
+		let len = self.len(min_size)?;
+		(0..len).map(|_| item(self)).collect()
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	fn temp_path(name: &str) -> std::path::PathBuf
+	{
+		std::env::temp_dir().join(format!("freen-{}-{}.bin", name, std::process::id()))
+	}
+
+	#[test]
+	fn buffers_keep_del_and_wide_chars()
+	{
+		let white = Color::new(1.0, 1.0, 1.0, 1.0);
+		let black = Color::new(0.0, 0.0, 0.0, 1.0);
+		let mut buffer = Buffer::new(4, 1);
+		buffer.writeText(0, 0, "\u{7F}漢e\u{301}", white, black);
+		let path = temp_path("del");
+		save_buffer(&buffer, &path).unwrap();
+		let loaded = load_buffer(&path).unwrap();
+		fs::remove_file(&path).unwrap();
+		assert_eq!(loaded.chars, buffer.chars);
+		assert_eq!(loaded.cell_text(0), "\u{7F}");
+		assert_eq!(loaded.cell_text(3), "e\u{301}");
+	}
+
+	fn file(version: u32, width: u32, height: u32, chars: Vec<u32>) -> BufferFile
+	{
+		let colors = chars.len() * 4;
+		BufferFile
+		{
+			magic: BUFFER_MAGIC.to_string(),
+			version,
+			width,
+			height,
+			chars,
+			foreground: vec![1.0; colors],
+			background: vec![0.0; colors],
+			cluster_cells: Vec::new(),
+			cluster_texts: Vec::new(),
+		}
+	}
+
+	fn load_file(name: &str, file: BufferFile) -> Result<Buffer, Box<dyn Error>>
+	{
+		let path = temp_path(name);
+		fs::write(&path, file.encode()).unwrap();
+		let loaded = load_buffer(&path);
+		fs::remove_file(&path).unwrap();
+		loaded
+	}
+
+	#[test]
+	fn version_1_placeholders_are_mapped()
+	{
+		let loaded = load_file("v1", file(1, 2, 1, vec!['漢' as u32, V1_WIDE_PLACEHOLDER])).unwrap();
+		assert_eq!(loaded.chars, ['漢', WIDE_PLACEHOLDER]);
+	}
+
+	#[test]
+	fn invalid_sizes_are_rejected()
+	{
+		let err = load_file("overflow", file(BUFFER_VERSION, u32::MAX, u32::MAX, vec![' ' as u32])).err().unwrap();
+		assert_eq!(err.to_string(), "Buffer size overflows");
+
+		let err = load_file("mismatch", file(BUFFER_VERSION, 65536, 65536, vec![' ' as u32; 4])).err().unwrap();
+		assert_eq!(err.to_string(), "Buffer data does not match its size");
+	}
+
+	#[test]
+	fn corrupt_files_are_rejected()
+	{
+		let bytes = file(BUFFER_VERSION, 2, 1, vec![' ' as u32; 2]).encode();
+		// Offset der Länge von chars hinter magic, version, width und height
+		let chars = 8 + BUFFER_MAGIC.len() + 12;
+		let path = temp_path("corrupt");
+		let load = |bytes: &[u8]| {
+			fs::write(&path, bytes).unwrap();
+			load_buffer(&path).err().unwrap().to_string()
+		};
+
+		// Eine riesige Länge darf keinen Speicher reservieren.
+		for len in [u64::MAX, u32::MAX as u64, 1 << 40]
+		{
+			let mut corrupt = bytes.clone();
+			corrupt[chars..chars+8].copy_from_slice(&len.to_le_bytes());
+			assert_eq!(load(&corrupt), "Invalid length in buffer file");
+		}
+		let mut corrupt = bytes.clone();
+		corrupt[..8].copy_from_slice(&u64::MAX.to_le_bytes());
+		assert_eq!(load(&corrupt), "Invalid length in buffer file");
+
+		assert_eq!(load(&bytes[..bytes.len()-1]), "Truncated buffer file");
+		assert_eq!(load(&[bytes.as_slice(), &[0]].concat()), "Trailing bytes in buffer file");
+		fs::remove_file(&path).unwrap();
+	}
+}
@@ -0,0 +1,402 @@
+use std::io::{self, Stdout, Write};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::time::Duration;
+
+use crossterm::{cursor, event, execute, queue, style, terminal};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use unicode_width::UnicodeWidthStr;
+use winit::event::VirtualKeyCode;
+
+use crate::*;
+use super::{Buffer, Color};
+use super::keys::{key_char, key_code};
+use super::screen::{EVENT_KEY_CHAR, EVENT_KEY_DOWN, EVENT_KEY_UP, EVENT_WINDOW_CLOSED, MOD_ALT, MOD_CTRL, MOD_SHIFT};
+
+/// Wartezeit auf Eingaben, bevor wieder auf Befehle geprüft wird.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Befehle vom Lua Thread an den Terminal Thread.
+pub enum TerminalCommand
+{
+	Redraw,
+	Close,
+}
+
+/// Zuletzt ausgegebener Inhalt einer Zelle.
+#[derive(Clone, PartialEq)]
+struct TerminalCell
+{
+	text: String,
+	fg: [u8; 3],
+	bg: [u8; 3],
+}
+
+/// Gibt den Buffer einer GPU T1 mit 24 Bit ANSI Farben im aktuellen Terminal aus.
+/// Es werden nur Zellen ausgegeben, die sich seit dem letzten Bild geändert haben.
+/// Tastatur Eingaben werden als OnKeyDown, OnKeyChar und OnKeyUp gemeldet.
+/// Terminals melden kein Loslassen, daher folgt OnKeyUp direkt auf OnKeyDown.
+/// Strg+C schließt das Terminal wie ein Fenster.
+/// Ist das Terminal kleiner als der Buffer, wird rechts und unten abgeschnitten.
+pub struct TerminalScreen<W: Write = Stdout>
+{
+	buffer: Arc<Mutex<Buffer>>,
+	emitter: Option<EventEmitter>,
+	out: W,
+	width: u32,
+	/// Spalten und Zeilen des Terminals
+	size: (u16, u16),
+	cells: Vec<TerminalCell>,
+}
+
+impl TerminalScreen
+{
+	pub fn new(buffer: Arc<Mutex<Buffer>>, emitter: Option<EventEmitter>) -> Self
+	{
+		Self::with_output(buffer, emitter, io::stdout(), (0, 0))
+	}
+}
+
+impl<W: Write> TerminalScreen<W>
+{
+	fn with_output(buffer: Arc<Mutex<Buffer>>, emitter: Option<EventEmitter>, out: W, size: (u16, u16)) -> Self
+	{
+		Self{ buffer, emitter, out, width: 0, size, cells: Vec::new() }
+	}
+
+	/// Läuft, bis das Terminal geschlossen wird oder der Bildschirm nicht mehr existiert.
+	pub fn run(&mut self, commands: Receiver<TerminalCommand>)
+	{
+		if let Err(e) = self.enter()
+		{
+			eprintln!("Terminal Error {}", e);
+			return;
+		}
+		if let Err(e) = self.event_loop(commands)
+		{
+			eprintln!("Terminal Error {}", e);
+		}
+		if let Err(e) = self.leave()
+		{
+			eprintln!("Terminal Error {}", e);
+		}
+	}
+
+	fn enter(&mut self) -> io::Result<()>
+	{
+		self.size = terminal::size()?;
+		terminal::enable_raw_mode()?;
+		execute!(self.out, terminal::EnterAlternateScreen, cursor::Hide, terminal::Clear(terminal::ClearType::All))
+	}
+
+	fn leave(&mut self) -> io::Result<()>
+	{
+		execute!(self.out, style::ResetColor, cursor::Show, terminal::LeaveAlternateScreen)?;
+		terminal::disable_raw_mode()
+	}
+
+	fn event_loop(&mut self, commands: Receiver<TerminalCommand>) -> io::Result<()>
+	{
+		self.draw()?;
+		loop
+		{
+			let mut redraw = false;
+			loop
+			{
+				match commands.try_recv()
+				{
+					Ok(TerminalCommand::Redraw) => redraw = true,
+					Ok(TerminalCommand::Close) | Err(TryRecvError::Disconnected) => return Ok(()),
+					Err(TryRecvError::Empty) => break,
+				}
+			}
+			if redraw
+			{
+				self.draw()?;
+			}
+
+			if event::poll(POLL_INTERVAL)?
+			{
+				match event::read()?
+				{
+					Event::Key(key) => {
+						if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)
+						{
+							self.send(Signal::noArgs(EVENT_WINDOW_CLOSED, self.owner()));
+							return Ok(());
+						}
+						self.handle_key(key);
+					},
+					Event::Resize(columns, rows) => self.resize((columns, rows))?,
+					_ => {}
+				}
+			}
+		}
+	}
+
+	/// Nach einer Größenänderung wird alles neu ausgegeben und neu abgeschnitten.
+	fn resize(&mut self, size: (u16, u16)) -> io::Result<()>
+	{
+		self.size = size;
+		self.cells.clear();
+		self.draw()
+	}
+
+	/// Gibt die geänderten Zellen aus, soweit sie in das Terminal passen.
+	fn draw(&mut self) -> io::Result<()>
+	{
+		let (width, cells) = {
+			let mut buffer = self.buffer.lock().unwrap();
+			let dirty = buffer.take_dirty();
+			if dirty.is_empty() && buffer.width == self.width && !self.cells.is_empty() { return Ok(()); }
+			(buffer.width, (0..buffer.chars.len()).map(|i| TerminalCell::new(&buffer, i)).collect::<Vec<_>>())
+		};
+		if width != self.width || cells.len() != self.cells.len()
+		{
+			self.width = width;
+			self.cells.clear();
+			queue!(self.out, style::ResetColor, terminal::Clear(terminal::ClearType::All))?;
+		}
+
+		let (columns, rows) = (self.size.0 as u32, self.size.1 as u32);
+		let mut cursor: Option<(u32, u32)> = None;
+		let mut colors: Option<([u8; 3], [u8; 3])> = None;
+		for (i, cell) in cells.iter().enumerate()
+		{
+			if self.cells.get(i) == Some(cell) { continue; }
+			// Die zweite Spalte breiter Zeichen wurde mit dem Zeichen ausgegeben.
+			if cell.text.is_empty() { cursor = None; continue; }
+
+			let (x, y) = (i as u32 % width, i as u32 / width);
+			if x >= columns || y >= rows { cursor = None; continue; }
+			// Ein breites Zeichen in der letzten Spalte würde umbrechen.
+			let cell_width = UnicodeWidthStr::width(cell.text.as_str()).max(1) as u32;
+			let text = match x + cell_width > columns
+			{
+				true => " ",
+				false => cell.text.as_str()
+			};
+			if cursor != Some((x, y))
+			{
+				queue!(self.out, cursor::MoveTo(x as u16, y as u16))?;
+			}
+			if colors != Some((cell.fg, cell.bg))
+			{
+				queue!(self.out,
+					style::SetForegroundColor(rgb(cell.fg)),
+					style::SetBackgroundColor(rgb(cell.bg)))?;
+				colors = Some((cell.fg, cell.bg));
+			}
+			queue!(self.out, style::Print(text))?;
+			cursor = Some((x + cell_width, y));
+		}
+		self.cells = cells;
+		self.out.flush()
+	}
+
+	fn handle_key(&mut self, key: KeyEvent)
+	{
+		if key.kind == KeyEventKind::Release { return; }
+		let modifiers = modifier_bits(key.modifiers);
+		let (ch, code) = match virtual_key(key.code)
+		{
+			Some(k) => (key_char(k), key_code(k)),
+			None => (0, 0)
+		};
+		self.send(Signal::numArgs(EVENT_KEY_DOWN, self.owner(), vec![ch, code, modifiers]));
+		if let KeyCode::Char(c) = key.code
+		{
+			if !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+			{
+				self.send(Signal::new(EVENT_KEY_CHAR, self.owner(), vec![
+					Value::from(c),
+					Value::from(modifiers)
+				]));
+			}
+		}
+		self.send(Signal::numArgs(EVENT_KEY_UP, self.owner(), vec![ch, code, modifiers]));
+	}
+
+	#[inline]
+	fn owner(&self) -> UID
+	{
+		self.emitter.as_ref().map(|e| e.owner()).unwrap_or_default()
+	}
+
+	#[inline]
+	fn send(&mut self, signal: Signal)
+	{
+		if let Some(emitter) = self.emitter.as_mut()
+		{
+			emitter.send(signal);
+		}
+	}
+}
+
+impl TerminalCell
+{
+	fn new(buffer: &Buffer, idx: usize) -> Self
+	{
+		let mut text = buffer.cell_text(idx);
+		// Leere Zellen und Steuerzeichen werden als Leerzeichen ausgegeben.
+		if text.chars().next().is_some_and(char::is_control)
+		{
+			text = " ".to_string();
+		}
+		Self{ text, fg: opaque(buffer.foreground[idx]), bg: opaque(buffer.background[idx]) }
+	}
+}
+
+/// Terminals kennen keine Transparenz. Farben werden auf Schwarz gemischt.
+#[inline]
+fn opaque(color: Color) -> [u8; 3]
+{
+	let [r, g, b, a]: [f32; 4] = color.into();
+	let a = a.clamp(0.0, 1.0);
+	[(r * a * 255.0) as u8, (g * a * 255.0) as u8, (b * a * 255.0) as u8]
+}
+
+#[inline]
+fn rgb(c: [u8; 3]) -> style::Color
+{
+	style::Color::Rgb{ r: c[0], g: c[1], b: c[2] }
+}
+
+fn modifier_bits(modifiers: KeyModifiers) -> i32
+{
+	let mut bits = 0;
+	if modifiers.contains(KeyModifiers::CONTROL) { bits |= MOD_CTRL; }
+	if modifiers.contains(KeyModifiers::SHIFT)   { bits |= MOD_SHIFT; }
+	if modifiers.contains(KeyModifiers::ALT)     { bits |= MOD_ALT; }
+	bits
+}
+
+/// Übersetzt eine Terminal Taste in die entsprechende winit Taste eines US Layouts.
+/// Zeichen mit Shift werden ihrer Grundtaste zugeordnet.
+fn virtual_key(code: KeyCode) -> Option<VirtualKeyCode>
+{
+	use VirtualKeyCode::*;
+	let key = match code
+	{
+		KeyCode::Backspace => Back,
+		KeyCode::Enter => Return,
+		KeyCode::Left => Left,
+		KeyCode::Right => Right,
+		KeyCode::Up => Up,
+		KeyCode::Down => Down,
+		KeyCode::Home => Home,
+		KeyCode::End => End,
+		KeyCode::PageUp => PageUp,
+		KeyCode::PageDown => PageDown,
+		KeyCode::Tab | KeyCode::BackTab => Tab,
+		KeyCode::Delete => Delete,
+		KeyCode::Insert => Insert,
+		KeyCode::Esc => Escape,
+		KeyCode::F(n) => match n
+		{
+			1 => F1, 2 => F2, 3 => F3, 4 => F4, 5 => F5, 6 => F6,
+			7 => F7, 8 => F8, 9 => F9, 10 => F10, 11 => F11, 12 => F12,
+			_ => return None
+		},
+		KeyCode::Char(c) => match c.to_ascii_uppercase()
+		{
+			'A' => A, 'B' => B, 'C' => C, 'D' => D, 'E' => E, 'F' => F, 'G' => G,
+			'H' => H, 'I' => I, 'J' => J, 'K' => K, 'L' => L, 'M' => M, 'N' => N,
+			'O' => O, 'P' => P, 'Q' => Q, 'R' => R, 'S' => S, 'T' => T, 'U' => U,
+			'V' => V, 'W' => W, 'X' => X, 'Y' => Y, 'Z' => Z,
+			'0' | ')' => Key0, '1' | '!' => Key1, '2' | '@' => Key2, '3' | '#' => Key3,
+			'4' | '$' => Key4, '5' | '%' => Key5, '6' | '^' => Key6, '7' | '&' => Key7,
+			'8' | '*' => Key8, '9' | '(' => Key9,
+			' ' => Space,
+			';' | ':' => Semicolon,
+			'=' | '+' => Equals,
+			',' | '<' => Comma,
+			'-' | '_' => Minus,
+			'.' | '>' => Period,
+			'/' | '?' => Slash,
+			'`' | '~' => Grave,
+			'[' | '{' => LBracket,
+			'\\' | '|' => Backslash,
+			']' | '}' => RBracket,
+			'\'' | '"' => Apostrophe,
+			_ => return None
+		},
+		_ => return None
+	};
+	Some(key)
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	fn screen(buffer: &Arc<Mutex<Buffer>>, size: (u16, u16)) -> TerminalScreen<Vec<u8>>
+	{
+		TerminalScreen::with_output(buffer.clone(), None, Vec::new(), size)
+	}
+
+	fn output(screen: &mut TerminalScreen<Vec<u8>>) -> String
+	{
+		String::from_utf8(std::mem::take(&mut screen.out)).unwrap()
+	}
+
+	/// Erwartete Ausgabe einer Zelle mit Cursor Sprung und Farben.
+	fn cell(out: &mut Vec<u8>, x: u16, y: u16, text: &str, fg: Color, bg: Color)
+	{
+		queue!(out, cursor::MoveTo(x, y),
+			style::SetForegroundColor(rgb(opaque(fg))),
+			style::SetBackgroundColor(rgb(opaque(bg))),
+			style::Print(text)).unwrap();
+	}
+
+	#[test]
+	fn draws_only_changed_cells_inside_the_terminal()
+	{
+		let white = Color::new(1.0, 1.0, 1.0, 1.0);
+		let black = Color::new(0.0, 0.0, 0.0, 1.0);
+		let red = Color::new(1.0, 0.0, 0.0, 1.0);
+		let buffer = Arc::new(Mutex::new(Buffer::new(4, 2)));
+		{
+			let mut b = buffer.lock().unwrap();
+			b.writeText(0, 0, "ab漢", white, black);
+			b.writeText(0, 1, "cdef", white, black);
+		}
+
+		// Das Terminal ist kleiner als der Buffer, das breite Zeichen passt nicht mehr.
+		let mut screen = screen(&buffer, (3, 1));
+		screen.draw().unwrap();
+		let mut expected = Vec::new();
+		queue!(expected, style::ResetColor, terminal::Clear(terminal::ClearType::All)).unwrap();
+		cell(&mut expected, 0, 0, "a", white, black);
+		queue!(expected, style::Print("b"), style::Print(" ")).unwrap();
+		assert_eq!(output(&mut screen), String::from_utf8(expected).unwrap());
+
+		// Danach wird nur die geänderte Zelle ausgegeben.
+		buffer.lock().unwrap().writeText(1, 0, "X", red, black);
+		screen.draw().unwrap();
+		let mut expected = Vec::new();
+		cell(&mut expected, 1, 0, "X", red, black);
+		assert_eq!(output(&mut screen), String::from_utf8(expected).unwrap());
+
+		// Ohne Änderungen wird nichts ausgegeben.
+		screen.draw().unwrap();
+		assert_eq!(output(&mut screen), "");
+
+		// Ein größeres Terminal zeigt den ganzen Buffer.
+		screen.resize((4, 2)).unwrap();
+		let mut expected = Vec::new();
+		queue!(expected, style::ResetColor, terminal::Clear(terminal::ClearType::All)).unwrap();
+		cell(&mut expected, 0, 0, "a", white, black);
+		queue!(expected,
+			style::SetForegroundColor(rgb(opaque(red))),
+			style::SetBackgroundColor(rgb(opaque(black))),
+			style::Print("X"),
+			style::SetForegroundColor(rgb(opaque(white))),
+			style::SetBackgroundColor(rgb(opaque(black))),
+			style::Print("漢")).unwrap();
+		queue!(expected, cursor::MoveTo(0, 1),
+			style::Print("c"), style::Print("d"), style::Print("e"), style::Print("f")).unwrap();
+		assert_eq!(output(&mut screen), String::from_utf8(expected).unwrap());
+	}
+}
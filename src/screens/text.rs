@@ -1,78 +1,634 @@
 
-use wgpu_glyph::{ab_glyph, GlyphBrushBuilder, Section, Text, Layout, GlyphBrush};
+use std::collections::HashMap;
+use std::num::{NonZeroU32, NonZeroU64};
+use std::ops::Range;
+use std::sync::OnceLock;
+
+use wgpu_glyph::ab_glyph::{self, Font, FontArc, OutlinedGlyph, PxScale, Rect as Rect2D, ScaleFont, point};
 use wgpu::{Device, TextureFormat, CommandEncoder, TextureView};
-use futures::task::SpawnExt;
 
-use super::{Buffer, ScreenSize};
+use super::{Buffer, Rect, ScreenSize, WIDE_PLACEHOLDER};
+use super::fonts::FontSet;
+
+/// Startgröße des Glyphen Atlas in Pixeln.
+const ATLAS_SIZE: u32 = 512;
+/// Abstand zwischen Glyphen im Atlas, damit beim Filtern keine Nachbarn durchscheinen.
+const ATLAS_PADDING: u32 = 1;
 
+/// Position und Größe der Glyphe relativ zur Zelle, Ausschnitt im Atlas und Farbe.
+type Instance = [f32; 12];
+const INSTANCE_SIZE: wgpu::BufferAddress = std::mem::size_of::<Instance>() as wgpu::BufferAddress;
+
+/// Zeichnet die Zeichen eines Buffers mit einer Instanz pro Zelle in einem einzigen Draw Call.
+/// Glyphen werden einmalig gerastert und in einem Atlas zwischengespeichert.
+/// Es werden nur die geänderten Bereiche des Buffers hochgeladen.
 pub struct TextGrid
 {
-	glyph_brush: GlyphBrush<()>,
 	size: ScreenSize,
-	staging_belt: wgpu::util::StagingBelt,
-	local_pool: futures::executor::LocalPool,
-	local_spawner: futures::executor::LocalSpawner
+	fonts: FontSet,
+	pipeline: wgpu::RenderPipeline,
+	bind_group_layout: wgpu::BindGroupLayout,
+	bind_group: wgpu::BindGroup,
+	sampler: wgpu::Sampler,
+	uniform_buffer: wgpu::Buffer,
+	instance_buffer: wgpu::Buffer,
+	atlas: GlyphAtlas,
+	instances: Vec<Instance>,
+	/// Erzwingt das Neuaufbauen aller Instanzen, z.B. nach Größenänderungen.
+	invalid: bool,
 }
 
 impl TextGrid
 {
 	pub fn new(device: &Device, size: ScreenSize, texture_format: TextureFormat) -> Self
 	{
-		let font = ab_glyph::FontArc::try_from_slice(include_bytes!("fira-mono.regular.ttf")).unwrap();
-		let glyph_brush = GlyphBrushBuilder::using_font(font).build(&device, texture_format);
+		let shader = device.create_shader_module(&wgpu::include_wgsl!("../shaders/text.wgsl"));
+		let fonts = FontSet::current();
+		let atlas = GlyphAtlas::new(device, ATLAS_SIZE);
+
+		let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+			label: Some("text:atlas_sampler"),
+			mag_filter: wgpu::FilterMode::Linear,
+			min_filter: wgpu::FilterMode::Linear,
+			..Default::default()
+		});
 
-		let staging_belt = wgpu::util::StagingBelt::new(1024);
-		let local_pool = futures::executor::LocalPool::new();
-		let local_spawner = local_pool.spawner();
+		let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("text:renderer_uniform_buffer"),
+			size: 48,
+			usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+			mapped_at_creation: false,
+		});
+
+		let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+			label: Some("text:renderer_bind_group_layout"),
+			entries: &[
+				wgpu::BindGroupLayoutEntry {
+					binding: 0,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Texture {
+						sample_type: wgpu::TextureSampleType::Float { filterable: true },
+						view_dimension: wgpu::TextureViewDimension::D2,
+						multisampled: false,
+					},
+					count: None,
+				},
+				wgpu::BindGroupLayoutEntry {
+					binding: 1,
+					visibility: wgpu::ShaderStages::FRAGMENT,
+					ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+					count: None,
+				},
+				wgpu::BindGroupLayoutEntry {
+					binding: 2,
+					visibility: wgpu::ShaderStages::VERTEX,
+					ty: wgpu::BindingType::Buffer {
+						ty: wgpu::BufferBindingType::Uniform,
+						has_dynamic_offset: false,
+						min_binding_size: NonZeroU64::new(48),
+					},
+					count: None,
+				},
+			],
+		});
+		let bind_group = TextGrid::create_bind_group(device, &bind_group_layout, &atlas, &sampler, &uniform_buffer);
+
+		let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+			label: Some("text:renderer_pipeline_layout"),
+			bind_group_layouts: &[&bind_group_layout],
+			push_constant_ranges: &[],
+		});
+		let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+			label: Some("text:renderer_pipeline"),
+			layout: Some(&pipeline_layout),
+			vertex: wgpu::VertexState {
+				module: &shader,
+				entry_point: "vs_main",
+				buffers: &[wgpu::VertexBufferLayout {
+					array_stride: INSTANCE_SIZE,
+					step_mode: wgpu::VertexStepMode::Instance,
+					attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4, 2 => Float32x4],
+				}],
+			},
+			primitive: wgpu::PrimitiveState::default(),
+			depth_stencil: None,
+			multisample: wgpu::MultisampleState::default(),
+			fragment: Some(wgpu::FragmentState {
+				module: &shader,
+				entry_point: "fs_main",
+				targets: &[wgpu::ColorTargetState {
+					format: texture_format,
+					blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+					write_mask: wgpu::ColorWrites::ALL,
+				}],
+			}),
+			multiview: None,
+		});
+
+		let instance_buffer = TextGrid::create_instance_buffer(device, &size);
 
 		Self
 		{
-			glyph_brush,
 			size,
-			staging_belt,
-			local_pool,
-			local_spawner,
+			fonts,
+			pipeline,
+			bind_group_layout,
+			bind_group,
+			sampler,
+			uniform_buffer,
+			instance_buffer,
+			atlas,
+			instances: Vec::new(),
+			invalid: true,
+		}
+	}
+
+	pub fn draw(&mut self, device: &Device, queue: &wgpu::Queue, encoder: &mut CommandEncoder, view: &TextureView, buffer: &Buffer, dirty: &[Rect])
+	{
+		if buffer.width != self.size.grid_width || buffer.height != self.size.grid_height { return; }
+		self.update(device, queue, buffer, dirty);
+
+		let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+			label: Some("text::pipeline render pass"),
+			color_attachments: &[wgpu::RenderPassColorAttachment {
+				view,
+				resolve_target: None,
+				ops: wgpu::Operations {
+					load: wgpu::LoadOp::Load,
+					store: true,
+				},
+			}],
+			depth_stencil_attachment: None,
+		});
+		rpass.set_pipeline(&self.pipeline);
+		rpass.set_bind_group(0, &self.bind_group, &[]);
+		rpass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+		rpass.draw(0..6, 0..self.instances.len() as u32);
+	}
+
+	pub fn resize(&mut self, device: &Device, size: ScreenSize)
+	{
+		if size.grid_width != self.size.grid_width || size.grid_height != self.size.grid_height
+		{
+			self.instance_buffer = TextGrid::create_instance_buffer(device, &size);
 		}
+		self.size = size;
+		self.invalid = true;
 	}
 
-	pub fn draw(&mut self, device: &Device, encoder: &mut CommandEncoder, view: &TextureView, buffer: &Buffer)
+	/// Aktualisiert Atlas und Instanzen und lädt geänderte Zellen hoch.
+	fn update(&mut self, device: &Device, queue: &wgpu::Queue, buffer: &Buffer, dirty: &[Rect])
 	{
-		let cell_size = self.size.cell_size();
+		let glyph_scale = self.size.font_size as f32 * self.size.viewport().scale;
+		if self.invalid
+		{
+			if self.atlas.cache.scale != glyph_scale
+			{
+				self.atlas.cache.clear(glyph_scale);
+			}
+			self.write_locals(queue);
+		}
 
-		for i in 0..buffer.chars.len()
+		let max_size = device.limits().max_texture_dimension_2d;
+		let mut evicted = false;
+		self.atlas.cache.skip_missing = false;
+		self.atlas.cache.skipped = false;
+		let ranges = loop
 		{
- 			if buffer.chars[i].is_whitespace() { continue; }
-			let x = ((i as u32 % buffer.width) * cell_size.width) as f32;
-			let y = ((i as u32 / buffer.width) * cell_size.height) as f32;
-			self.glyph_brush.queue(Section {
-				screen_position: (x, y),
-				bounds: (cell_size.width as f32, cell_size.height as f32),
-				text: vec![Text::new(&buffer.chars[i].to_string()).with_color(buffer.foreground[i]).with_scale(self.size.font_size as f32)],
-				layout: Layout::default_single_line()
+			let full = [buffer.bounds()];
+			let regions = match self.invalid || self.instances.len() != buffer.chars.len()
+			{
+				true => &full[..],
+				false => dirty
+			};
+			let texture = &self.atlas.texture;
+			let mut upload = |x: u32, y: u32, width: u32, height: u32, pixels: &[u8]| write_glyph(queue, texture, x, y, width, height, pixels);
+			match update_instances(&mut self.instances, &mut self.atlas.cache, &self.fonts, buffer, regions, &mut upload)
+			{
+				Ok(ranges) => break ranges,
+				Err(AtlasFull) => match atlas_step(self.atlas.cache.width, max_size, evicted)
+				{
+					AtlasStep::Grow(size) => {
+						// Der Atlas wird vergrößert und alle Glyphen neu gerastert.
+						self.atlas = GlyphAtlas::new(device, size);
+						self.atlas.cache.clear(glyph_scale);
+						self.bind_group = TextGrid::create_bind_group(device, &self.bind_group_layout, &self.atlas, &self.sampler, &self.uniform_buffer);
+						self.write_locals(queue);
+						self.invalid = true;
+					},
+					AtlasStep::Evict => {
+						evicted = true;
+						self.atlas.cache.clear(glyph_scale);
+						self.invalid = true;
+					},
+					AtlasStep::Skip => self.atlas.cache.skip_missing = true,
+				}
+			}
+		};
+
+		for range in ranges
+		{
+			queue.write_buffer(&self.instance_buffer, range.start as u64 * INSTANCE_SIZE, bytemuck::cast_slice(&self.instances[range]));
+		}
+		// Leer gebliebene Zellen werden im nächsten Frame erneut versucht,
+		// sonst blieben sie leer, bis das Skript sie wieder beschreibt.
+		self.invalid = self.atlas.cache.skipped;
+	}
+
+	fn write_locals(&self, queue: &wgpu::Queue)
+	{
+		let viewport = self.size.viewport();
+		let cell = self.size.cell_size();
+		let locals: [f32; 12] = [
+			self.size.surface_width as f32, self.size.surface_height as f32, viewport.x, viewport.y,
+			cell.width as f32 * viewport.scale, cell.height as f32 * viewport.scale, self.atlas.cache.width as f32, self.atlas.cache.height as f32,
+			self.size.grid_width.max(1) as f32, 0.0, 0.0, 0.0,
+		];
+		queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&locals));
+	}
+
+	fn create_instance_buffer(device: &Device, size: &ScreenSize) -> wgpu::Buffer
+	{
+		let cells = (size.grid_width * size.grid_height).max(1) as u64;
+		device.create_buffer(&wgpu::BufferDescriptor {
+			label: Some("text:renderer_instance_buffer"),
+			size: cells * INSTANCE_SIZE,
+			usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+			mapped_at_creation: false,
+		})
+	}
+
+	fn create_bind_group(device: &Device, layout: &wgpu::BindGroupLayout, atlas: &GlyphAtlas, sampler: &wgpu::Sampler, uniform_buffer: &wgpu::Buffer) -> wgpu::BindGroup
+	{
+		let view = atlas.texture.create_view(&wgpu::TextureViewDescriptor::default());
+		device.create_bind_group(&wgpu::BindGroupDescriptor {
+			label: Some("text:renderer_bind_group"),
+			layout,
+			entries: &[
+				wgpu::BindGroupEntry {
+					binding: 0,
+					resource: wgpu::BindingResource::TextureView(&view),
+				},
+				wgpu::BindGroupEntry {
+					binding: 1,
+					resource: wgpu::BindingResource::Sampler(sampler),
+				},
+				wgpu::BindGroupEntry {
+					binding: 2,
+					resource: uniform_buffer.as_entire_binding(),
+				},
+			],
+		})
+	}
+}
+
+/// Baut die Instanzen der Bereiche neu auf und gibt die geänderten Abschnitte des Instanz Buffers zurück.
+fn update_instances<U>(instances: &mut Vec<Instance>, cache: &mut GlyphCache, fonts: &FontSet, buffer: &Buffer, regions: &[Rect], upload: &mut U) -> Result<Vec<Range<usize>>, AtlasFull>
+	where U: FnMut(u32, u32, u32, u32, &[u8])
+{
+	instances.resize(buffer.chars.len(), [0.0; 12]);
+
+	let mut ranges = Vec::new();
+	for rect in regions
+	{
+		let rect = rect.intersect(&buffer.bounds());
+		if rect.area() == 0 { continue; }
+		for y in rect.y..(rect.y + rect.height)
+		{
+			let start = (y * buffer.width + rect.x) as usize;
+			let range = start..(start + rect.width as usize);
+			for i in range.clone()
+			{
+				let glyph = match buffer.cluster(i)
+				{
+					Some(cluster) => cache.cluster(fonts, cluster, upload)?,
+					None => cache.glyph(fonts, buffer.chars[i], upload)?
+				};
+				instances[i] = match glyph
+				{
+					Some(g) => {
+						let color: [f32; 4] = buffer.foreground[i].into();
+						[g.offset[0], g.offset[1], g.size[0], g.size[1],
+						 g.uv[0], g.uv[1], g.size[0], g.size[1],
+						 color[0], color[1], color[2], color[3]]
+					},
+					None => [0.0; 12]
+				};
+			}
+			ranges.push(range);
+		}
+	}
+	Ok(ranges)
+}
+
+fn write_glyph(queue: &wgpu::Queue, texture: &wgpu::Texture, x: u32, y: u32, width: u32, height: u32, pixels: &[u8])
+{
+	queue.write_texture(
+		wgpu::ImageCopyTexture {
+			texture,
+			mip_level: 0,
+			origin: wgpu::Origin3d { x, y, z: 0 },
+			aspect: wgpu::TextureAspect::All,
+		},
+		pixels,
+		wgpu::ImageDataLayout {
+			offset: 0,
+			bytes_per_row: NonZeroU32::new(width),
+			rows_per_image: NonZeroU32::new(height),
+		},
+		wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+	);
+}
+
+/// Der Atlas hat keinen Platz für eine weitere Glyphe.
+struct AtlasFull;
+
+/// Reaktion auf einen vollen Atlas.
+#[derive(Debug, PartialEq)]
+enum AtlasStep
+{
+	/// Einen größeren Atlas anlegen.
+	Grow(u32),
+	/// Alle Glyphen verwerfen und nur die aktuell sichtbaren neu rastern.
+	Evict,
+	/// Glyphen ohne Platz in diesem Bild nicht zeichnen.
+	Skip,
+}
+
+/// Der Atlas wächst bis zur maximalen Texturgröße. Danach wird er einmal geleert,
+/// passen die Glyphen dann immer noch nicht, werden die fehlenden übersprungen.
+fn atlas_step(width: u32, max_size: u32, evicted: bool) -> AtlasStep
+{
+	if width < max_size { AtlasStep::Grow((width * 2).min(max_size)) }
+	else if !evicted { AtlasStep::Evict }
+	else { AtlasStep::Skip }
+}
+
+#[derive(Clone, Copy)]
+struct GlyphEntry
+{
+	/// Position relativ zur oberen linken Ecke der Zelle
+	offset: [f32; 2],
+	size: [f32; 2],
+	/// Position im Atlas
+	uv: [f32; 2],
+}
+
+/// Textur mit gerasterten Glyphen.
+struct GlyphAtlas
+{
+	texture: wgpu::Texture,
+	cache: GlyphCache,
+}
+
+impl GlyphAtlas
+{
+	fn new(device: &Device, size: u32) -> Self
+	{
+		let size = size.min(device.limits().max_texture_dimension_2d);
+		let texture = device.create_texture(&wgpu::TextureDescriptor {
+			label: Some("text:atlas_texture"),
+			size: wgpu::Extent3d { width: size, height: size, depth_or_array_layers: 1 },
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: wgpu::TextureDimension::D2,
+			format: wgpu::TextureFormat::R8Unorm,
+			usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+		});
+		Self{ texture, cache: GlyphCache::new(size) }
+	}
+}
+
+/// Belegung des Atlas, zeilenweise gepackt. Die Pixel werden über eine Upload Funktion geschrieben.
+struct GlyphCache
+{
+	width: u32,
+	height: u32,
+	cursor_x: u32,
+	cursor_y: u32,
+	row_height: u32,
+	/// Zeichen ohne sichtbare Glyphe werden als None gespeichert.
+	glyphs: HashMap<char, Option<GlyphEntry>>,
+	clusters: HashMap<String, Option<GlyphEntry>>,
+	scale: f32,
+	/// Neue Glyphen werden nicht gerastert, sondern leer gezeichnet.
+	skip_missing: bool,
+	/// Wird gesetzt, wenn wegen skip_missing eine Zelle leer geblieben ist.
+	skipped: bool,
+}
+
+impl GlyphCache
+{
+	fn new(size: u32) -> Self
+	{
+		Self{ width: size, height: size, cursor_x: 0, cursor_y: 0, row_height: 0, glyphs: HashMap::new(), clusters: HashMap::new(), scale: 0.0, skip_missing: false, skipped: false }
+	}
+
+	/// Verwirft alle Glyphen, z.B. wenn sich die Skalierung ändert.
+	fn clear(&mut self, scale: f32)
+	{
+		self.glyphs.clear();
+		self.clusters.clear();
+		self.cursor_x = 0;
+		self.cursor_y = 0;
+		self.row_height = 0;
+		self.scale = scale;
+	}
+
+	fn glyph<U>(&mut self, fonts: &FontSet, ch: char, upload: &mut U) -> Result<Option<GlyphEntry>, AtlasFull>
+		where U: FnMut(u32, u32, u32, u32, &[u8])
+	{
+		if let Some(entry) = self.glyphs.get(&ch) { return Ok(*entry); }
+		if self.skip_missing { self.skipped = true; return Ok(None); }
+		let entry = match ch.is_whitespace() || ch.is_control() || ch == WIDE_PLACEHOLDER
+		{
+			true => None,
+			false => self.rasterize(fonts, ch.encode_utf8(&mut [0; 4]), upload)?
+		};
+		self.glyphs.insert(ch, entry);
+		Ok(entry)
+	}
+
+	/// Wie glyph, für Zellen mit einem Cluster aus mehreren Zeichen.
+	fn cluster<U>(&mut self, fonts: &FontSet, cluster: &str, upload: &mut U) -> Result<Option<GlyphEntry>, AtlasFull>
+		where U: FnMut(u32, u32, u32, u32, &[u8])
+	{
+		if let Some(entry) = self.clusters.get(cluster) { return Ok(*entry); }
+		if self.skip_missing { self.skipped = true; return Ok(None); }
+		let entry = self.rasterize(fonts, cluster, upload)?;
+		self.clusters.insert(cluster.to_string(), entry);
+		Ok(entry)
+	}
+
+	/// Rastert alle Zeichen des Textes gemeinsam in einen Ausschnitt des Atlas.
+	fn rasterize<U>(&mut self, fonts: &FontSet, text: &str, upload: &mut U) -> Result<Option<GlyphEntry>, AtlasFull>
+		where U: FnMut(u32, u32, u32, u32, &[u8])
+	{
+		let outlines = layout_cluster(fonts, text, PxScale::from(self.scale), 0.0, 0.0);
+		let bounds = match outlines.iter().map(|o| o.px_bounds()).reduce(|a, b| Rect2D{
+			min: point(a.min.x.min(b.min.x), a.min.y.min(b.min.y)),
+			max: point(a.max.x.max(b.max.x), a.max.y.max(b.max.y)),
+		})
+		{
+			Some(b) => b,
+			None => return Ok(None)
+		};
+		let (width, height) = (bounds.width().ceil() as u32, bounds.height().ceil() as u32);
+		if width == 0 || height == 0 { return Ok(None); }
+
+		if self.cursor_x + width + ATLAS_PADDING > self.width
+		{
+			self.cursor_x = 0;
+			self.cursor_y += self.row_height + ATLAS_PADDING;
+			self.row_height = 0;
+		}
+		if self.cursor_y + height + ATLAS_PADDING > self.height || width + ATLAS_PADDING > self.width
+		{
+			return Err(AtlasFull);
+		}
+
+		let mut pixels = vec![0u8; (width * height) as usize];
+		for outline in &outlines
+		{
+			let ox = (outline.px_bounds().min.x - bounds.min.x) as u32;
+			let oy = (outline.px_bounds().min.y - bounds.min.y) as u32;
+			outline.draw(|x, y, coverage| {
+				let (x, y) = (x + ox, y + oy);
+				if x < width && y < height
+				{
+					let pixel = &mut pixels[(y * width + x) as usize];
+					*pixel = (*pixel).max((coverage.clamp(0.0, 1.0) * 255.0) as u8);
+				}
 			});
 		}
+		upload(self.cursor_x, self.cursor_y, width, height, &pixels);
 
-		self.glyph_brush
-		.draw_queued(
-			&device,
-			&mut self.staging_belt,
-			encoder,
-			view,
-			self.size.window_width,
-			self.size.window_height,
-		)
-		.expect("Draw queued");
-		self.staging_belt.finish();
+		let entry = GlyphEntry
+		{
+			offset: [bounds.min.x, bounds.min.y],
+			size: [width as f32, height as f32],
+			uv: [self.cursor_x as f32, self.cursor_y as f32],
+		};
+		self.cursor_x += width + ATLAS_PADDING;
+		self.row_height = self.row_height.max(height);
+		Ok(Some(entry))
 	}
+}
 
-	pub fn clean_frame(&mut self)
+/// Ordnet die Zeichen eines Clusters ab der Position nebeneinander an.
+/// Kombinierende Zeichen haben keinen Vorschub und liegen so über ihrem Basiszeichen.
+/// Die Grundlinie richtet sich nach der Schriftart des ersten Zeichens.
+pub fn layout_cluster(fonts: &FontSet, text: &str, scale: PxScale, x: f32, y: f32) -> Vec<OutlinedGlyph>
+{
+	let mut outlines = Vec::new();
+	let mut pen = x;
+	let mut baseline = None;
+	for ch in text.chars()
 	{
-		self.local_spawner.spawn(self.staging_belt.recall()).expect("Recall staging belt");
-		self.local_pool.run_until_stalled();
+		let font = &fonts.fonts[fonts.font_for(ch)];
+		let scaled = font.as_scaled(scale);
+		let baseline = *baseline.get_or_insert(y + scaled.ascent());
+		let id = font.glyph_id(ch);
+		if !(ch.is_whitespace() || ch.is_control() || ch == WIDE_PLACEHOLDER)
+		{
+			outlines.extend(font.outline_glyph(id.with_scale_and_position(scale, point(pen, baseline))));
+		}
+		pen += scaled.h_advance(id);
 	}
+	outlines
+}
 
-	pub fn resize(&mut self, _device: &Device, size: ScreenSize)
+/// Die eingebettete Standard Schriftart. Sie wird nur einmal gelesen.
+pub fn default_font() -> FontArc
+{
+	static DEFAULT_FONT: OnceLock<FontArc> = OnceLock::new();
+	DEFAULT_FONT.get_or_init(|| ab_glyph::FontArc::try_from_slice(include_bytes!("fira-mono.regular.ttf")).unwrap()).clone()
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use super::super::Color;
+
+	/// Füllt die Instanzen wie TextGrid::update, nur mit einem Atlas ohne Textur.
+	fn fill(cache: &mut GlyphCache, max_size: u32, buffer: &Buffer) -> (Vec<Instance>, usize)
 	{
-		self.size = size;
+		let fonts = FontSet::current();
+		let scale = cache.scale;
+		let mut instances = Vec::new();
+		let mut evicted = false;
+		let mut uploads = 0;
+		cache.skip_missing = false;
+		cache.skipped = false;
+		for _ in 0..16
+		{
+			let mut upload = |_x: u32, _y: u32, _w: u32, _h: u32, _p: &[u8]| uploads += 1;
+			match update_instances(&mut instances, cache, &fonts, buffer, &[buffer.bounds()], &mut upload)
+			{
+				Ok(_) => return (instances, uploads),
+				Err(AtlasFull) => match atlas_step(cache.width, max_size, evicted)
+				{
+					AtlasStep::Grow(size) => { *cache = GlyphCache::new(size); cache.clear(scale); },
+					AtlasStep::Evict => { evicted = true; cache.clear(scale); },
+					AtlasStep::Skip => cache.skip_missing = true,
+				}
+			}
+		}
+		panic!("atlas did not settle");
 	}
-}
\ No newline at end of file
+
+	#[test]
+	fn full_atlas_skips_missing_glyphs()
+	{
+		let white = Color::new(1.0, 1.0, 1.0, 1.0);
+		let black = Color::new(0.0, 0.0, 0.0, 1.0);
+		let mut buffer = Buffer::new(26, 2);
+		for (i, ch) in ('A'..='Z').chain('a'..='z').enumerate()
+		{
+			buffer.write(i as i32 % 26, i as i32 / 26, ch, white, black);
+		}
+
+		// Der Atlas ist bereits so groß wie erlaubt.
+		let mut cache = GlyphCache::new(64);
+		cache.clear(32.0);
+		let (instances, uploads) = fill(&mut cache, 64, &buffer);
+		assert_eq!(instances.len(), 52);
+		assert!(uploads > 0);
+		assert!(instances[0][2] > 0.0);
+		assert!(instances.iter().any(|i| i[2] == 0.0));
+		// Die leeren Zellen werden gemeldet, damit sie später neu gezeichnet werden.
+		assert!(cache.skipped);
+
+		// Mit genug Platz wächst der Atlas und alle Glyphen werden gezeichnet.
+		let mut cache = GlyphCache::new(64);
+		cache.clear(32.0);
+		let (instances, _) = fill(&mut cache, 4096, &buffer);
+		assert!(cache.width > 64);
+		assert!(instances.iter().all(|i| i[2] > 0.0));
+		assert!(!cache.skipped);
+	}
+
+	#[test]
+	fn clusters_are_rasterized_together()
+	{
+		let fonts = FontSet::current();
+		let mut cache = GlyphCache::new(256);
+		cache.clear(32.0);
+		let mut upload = |_x: u32, _y: u32, _w: u32, _h: u32, _p: &[u8]| {};
+		let plain = cache.glyph(&fonts, 'e', &mut upload).ok().flatten().unwrap();
+		let accent = cache.cluster(&fonts, "e\u{301}", &mut upload).ok().flatten().unwrap();
+		// Der Akzent liegt über dem e, der Ausschnitt ist höher.
+		assert!(accent.size[1] > plain.size[1]);
+		assert!(accent.offset[1] < plain.offset[1]);
+	}
+
+	#[test]
+	fn atlas_grows_then_evicts_then_skips()
+	{
+		assert_eq!(atlas_step(512, 2048, false), AtlasStep::Grow(1024));
+		assert_eq!(atlas_step(1536, 2048, false), AtlasStep::Grow(2048));
+		assert_eq!(atlas_step(2048, 2048, false), AtlasStep::Evict);
+		assert_eq!(atlas_step(2048, 2048, true), AtlasStep::Skip);
+	}
+}
@@ -0,0 +1,32 @@
+// Vertex shader bindings
+struct VertexOutput {
+    [[location(0)]] color: vec4<f32>;
+    [[builtin(position)]] position: vec4<f32>;
+};
+
+struct Locals {
+    screen_size: vec2<f32>;
+    offset: vec2<f32>;
+    scale: vec2<f32>;
+    padding: vec2<f32>;
+};
+[[group(0), binding(0)]] var<uniform> r_locals: Locals;
+
+[[stage(vertex)]]
+fn vs_main(
+    [[location(0)]] position: vec2<f32>,
+    [[location(1)]] color: vec4<f32>,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.color = color;
+    // Pixel Koordinaten mit Ursprung oben links, skaliert in den Viewport
+    let pixel = position * r_locals.scale + r_locals.offset;
+    let ndc = pixel / r_locals.screen_size * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
+    out.position = vec4<f32>(ndc, 0.0, 1.0);
+    return out;
+}
+
+[[stage(fragment)]]
+fn fs_main([[location(0)]] color: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    return color;
+}
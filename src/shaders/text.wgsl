@@ -0,0 +1,50 @@
+// Vertex shader bindings
+struct VertexOutput {
+    [[location(0)]] tex_coord: vec2<f32>;
+    [[location(1)]] color: vec4<f32>;
+    [[builtin(position)]] position: vec4<f32>;
+};
+
+struct Locals {
+    // Größe der Zeichenfläche, Position des Viewports
+    surface: vec4<f32>;
+    // Skalierte Zellgröße, Größe des Atlas
+    cell: vec4<f32>;
+    // Breite des Rasters in Zellen
+    grid: vec4<f32>;
+};
+[[group(0), binding(2)]] var<uniform> r_locals: Locals;
+
+[[stage(vertex)]]
+fn vs_main(
+    [[builtin(vertex_index)]] vertex: u32,
+    [[builtin(instance_index)]] instance: u32,
+    [[location(0)]] glyph: vec4<f32>,
+    [[location(1)]] uv: vec4<f32>,
+    [[location(2)]] color: vec4<f32>,
+) -> VertexOutput {
+    // Zwei Dreiecke pro Zelle
+    let corner = vec2<f32>(
+        select(0.0, 1.0, vertex == 1u || vertex == 4u || vertex == 5u),
+        select(0.0, 1.0, vertex == 2u || vertex == 3u || vertex == 5u),
+    );
+    let columns = u32(r_locals.grid.x);
+    let cell = vec2<f32>(f32(instance % columns), f32(instance / columns));
+
+    let pixel = r_locals.surface.zw + cell * r_locals.cell.xy + glyph.xy + corner * glyph.zw;
+    var out: VertexOutput;
+    out.position = vec4<f32>(pixel / r_locals.surface.xy * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    out.tex_coord = (uv.xy + corner * uv.zw) / r_locals.cell.zw;
+    out.color = color;
+    return out;
+}
+
+// Fragment shader bindings
+[[group(0), binding(0)]] var r_atlas: texture_2d<f32>;
+[[group(0), binding(1)]] var r_atlas_sampler: sampler;
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let coverage = textureSample(r_atlas, r_atlas_sampler, in.tex_coord).r;
+    return vec4<f32>(in.color.rgb, in.color.a * coverage);
+}
@@ -96,6 +96,68 @@ function Test_FIN_API:TestGPU()
 	local buf = gpu:getBuffer()
 	local bw, bh = buf:getSize()
 	lu.assertEquals(bw, 100)
+	-- Screenshots funktionieren auch ohne gebundenen Bildschirm.
+	local file = os.tmpname()
+	lu.assertTrue(gpu:screenshot(file))
+	os.remove(file)
+	-- Aufnahmen zeichnen jeden flush auf, das Format folgt der Dateiendung.
+	lu.assertFalse(gpu:startRecording(file))
+	lu.assertTrue(gpu:startRecording(file..".png"))
+	gpu:flush()
+	gpu:flush()
+	lu.assertEquals(gpu:stopRecording(), 2)
+	lu.assertNil(gpu:stopRecording())
+	os.remove(file..".png")
+	-- GIF verwirft Bilder unter 10ms, das letzte Bild bleibt erhalten.
+	lu.assertTrue(gpu:startRecording(file..".gif"))
+	gpu:flush()
+	gpu:flush()
+	local frames = gpu:stopRecording()
+	lu.assertTrue(frames == 1 or frames == 2)
+	os.remove(file..".gif")
+	-- Snapshots vergleichen den ganzen Buffer.
+	buf:setText(0, 0, "Snapshot", 1, 0)
+	lu.assertTrue(buf:saveSnapshot(file))
+	lu.assertEquals(buf:compareSnapshot(file), {})
+	buf:set(1, 0, "N", 1, 0)
+	local diff = buf:compareSnapshot(file)
+	lu.assertEquals(diff[1], "1 cells differ")
+	lu.assertEquals(diff[2], "(1, 0): char 'n' != 'N'")
+	os.remove(file)
+	-- Kopieren mit Text Blend Mode, Leerzeichen der Quelle überschreiben nichts.
+	buf:fill(0, 0, bw, bh, " ", 1, 0)
+	local other = buf:clone()
+	other:setText(0, 0, "A B", 1, 0)
+	buf:setText(0, 0, "xyz", 1, 0)
+	buf:copy(0, 0, other, 1, 0, 0)
+	lu.assertEquals(buf:get(0, 0), "A")
+	lu.assertEquals(buf:get(1, 0), "y")
+	-- Quellen außerhalb des Ziels werden abgeschnitten.
+	buf:copy(bw + 5, -3, other, 0, 0, 0)
+	buf:copy(-500, 0, other, 0, 0, 0)
+	lu.assertEquals(buf:get(2, 0), "B")
+	-- setRaw schreibt je Zeichen eigene Farben.
+	lu.assertTrue(buf:setRaw(0, 1, "ab", {1, 0, 0, 1, 0, 1, 0, 1}, {0, 0, 0, 1, 0, 0, 0, 1}))
+	local t, f = buf:get(1, 1)
+	lu.assertEquals(t, "b")
+	lu.assertEquals(f.g, 1)
+	lu.assertFalse(buf:setRaw(0, 1, "abc", {1, 0, 0, 1}, {0, 0, 0, 1}))
+	-- Breite Zeichen belegen zwei Zellen, Graphem Cluster bleiben zusammen.
+	buf:setText(0, 2, "日本é!", 1, 0)
+	lu.assertEquals(buf:get(0, 2), "日")
+	lu.assertEquals(buf:get(1, 2), "")
+	lu.assertEquals(buf:get(2, 2), "本")
+	lu.assertEquals(buf:get(4, 2), "é")
+	lu.assertEquals(buf:get(5, 2), "!")
+	-- Gespeicherte Buffer werden exakt wieder geladen.
+	file = os.tmpname()
+	lu.assertTrue(buf:save(file))
+	local loaded = FREEN.loadBuffer(file)
+	lu.assertEquals({loaded:getSize()}, {bw, bh})
+	lu.assertEquals(loaded:get(0, 2), "日")
+	lu.assertEquals(loaded:get(4, 2), buf:get(4, 2))
+	os.remove(file)
+	lu.assertNil(FREEN.loadBuffer(file))
 	-- Large Screen
 	cls = findClass("Screen")
 	lu.assertEquals(cls.name, "Freen") -- Ohne Freen: Build_Screen_C
@@ -104,6 +166,12 @@ end
 function Test_FIN_API:TestGPU2()
 	local gpu = computer.getPCIDevices(findClass("GPUT1"))[1]
 	local screen = computer.getPCIDevices(findClass("Screen"))[1]
+	-- Monitore lassen sich schon vor dem Öffnen des Fensters abfragen, ohne Display gibt es nil.
+	local monitors = screen:getMonitors()
+	if monitors ~= nil and #monitors > 0 then
+		lu.assertTrue(monitors[1].width > 0)
+		screen:setMonitor(1)
+	end
 	gpu:bindScreen(screen)
 	local w,h = gpu:getSize()
 
@@ -129,6 +197,28 @@ function Test_FIN_API:TestGPU2()
 	--screen:close()
 end
 
+function Test_FIN_API:TestGPUT2()
+	local cls = findClass("GPUT2")
+	lu.assertEquals(cls.name, "GPU_T2_C")
+	local gpu = computer.getPCIDevices(cls)[1]
+	-- Die Bildschirmgröße ist in Pixeln angegeben.
+	local size = gpu:getScreenSize()
+	lu.assertEquals(size.x, FREEN.gpu2Width)
+	lu.assertEquals(size.y, FREEN.gpu2Height)
+	-- Längere Texte sind breiter.
+	local short = gpu:measureText("Hallo", 20, false)
+	local long = gpu:measureText("Hallo Welt", 20, false)
+	lu.assertTrue(short.x > 0)
+	lu.assertTrue(long.x > short.x)
+	-- Zeichenbefehle funktionieren auch ohne Bildschirm.
+	gpu:pushTransform({x=10, y=10}, 45, {x=1, y=1})
+	gpu:drawRect({x=0, y=0}, {x=100, y=50}, {r=1, g=0, b=0, a=1}, "", 0)
+	gpu:drawLines({{x=0, y=0}, {x=50, y=50}, {x=100, y=0}}, 2, {r=0, g=1, b=0, a=1})
+	gpu:drawBox({position={x=0, y=0}, size={x=20, y=20}, isRounded=true, radii={x=4, y=4, z=4, w=4}})
+	gpu:popGeometry()
+	gpu:flush()
+end
+
 function Test_FIN_API:TestEvents()
 	local comp = component.proxy(component.findComponent("Blub")[1])
 	-- Wir überschreiben den Dummy mit dem Event Handler
@@ -203,6 +293,60 @@ function Test_FIN_API:TestNetwork()
 	lu.assertEquals(p, port)
 	lu.assertEquals(m1, "Hallo 2")
 	lu.assertEquals(m2, "M\02")
+	-- Argumente behalten ihren Typ, auch nil, Arrays, Structs und Komponenten.
+	sender:send(reciever.id, port, true, nil, 1.5, {1, "a"}, {r=1, g=0.5}, sender)
+	local args = {select(5, event.pull(0.1))}
+	lu.assertEquals(args[1], true)
+	lu.assertNil(args[2])
+	lu.assertEquals(args[3], 1.5)
+	lu.assertEquals(args[4], {1, "a"})
+	lu.assertEquals(args[5], {r=1, g=0.5})
+	lu.assertEquals(args[6], sender)
+	-- Die Anzahl der Argumente ist nicht begrenzt.
+	local many = {}
+	for i = 1,20 do many[i] = i end
+	sender:send(reciever.id, port, table.unpack(many))
+	args = {select(5, event.pull(0.1))}
+	lu.assertEquals(args, many)
+	-- Signale ignorierter nativer Komponenten werden verworfen.
+	event.ignore(reciever)
+	lu.assertNotTableContains(event.listening(), reciever)
+	sender:send(reciever.id, port, "ignoriert")
+	lu.assertNil(event.pull(0.1))
+	event.listen(reciever)
+	lu.assertTableContains(event.listening(), reciever)
+	-- waitFor überspringt unpassende Signale, event.pull liefert sie danach.
+	local pings = event.filter{event="NetworkMessage", sender=reciever, values={port=port, [3]="ping"}}
+	sender:send(reciever.id, port, "pong")
+	sender:send(reciever.id, port, "ping")
+	lu.assertEquals(select(5, event.waitFor(pings, 0.1)), "ping")
+	lu.assertNil(event.waitFor(pings, 0.1))
+	lu.assertEquals(select(5, event.pull(0.1)), "pong")
+	-- Filter lassen sich verknüpfen und negieren.
+	local pongs = event.filter{values={[3]="pong"}}
+	-- Tabellen mit Array Einträgen und Feldern würden Werte verlieren.
+	lu.assertError(event.filter, {values={"ping", port=port}})
+	lu.assertError(sender.send, sender, reciever.id, port, {"ping", port=port})
+	sender:send(reciever.id, port, "pong")
+	lu.assertNil(event.waitFor(pings * pongs, 0.1))
+	lu.assertEquals(select(5, event.waitFor(pings + pongs, 0.1)), "pong")
+	sender:send(reciever.id, port, "ping")
+	lu.assertNil(event.waitFor(-pings, 0.1))
+	lu.assertEquals(select(5, event.pull(0.1)), "ping")
+	-- Queues sammeln Kopien ab ihrer Erstellung.
+	local queue = event.queue(pongs)
+	sender:send(reciever.id, port, "ping")
+	sender:send(reciever.id, port, "pong")
+	lu.assertEquals(select(5, queue:pull(0.1)), "pong")
+	lu.assertNil(queue:pull(0.1))
+	lu.assertEquals(select(5, event.pull(0.1)), "ping")
+	lu.assertEquals(select(5, event.pull(0.1)), "pong")
+	-- Listener werden beim Lesen passender Signale aufgerufen.
+	local heard = nil
+	event.registerListener(pings, function(e, c, s, p, m) heard = m end)
+	sender:send(reciever.id, port, "ping")
+	event.pull(0.1)
+	lu.assertEquals(heard, "ping")
 	reciever:close(port)
 end
 